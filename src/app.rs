@@ -1,90 +1,344 @@
 use specs::prelude::*;
 use crate::{
-    CELL_WIDTH,
-    CELL_HEIGHT,
     Color,
     components::*,
     damage::Damage,
-    FontCache,
-    map::{
-        Map,
-        TileType,
+    detection::Detection,
+    explore,
+    factions::{
+        FactionTable,
+        Reaction,
+        reaction_between,
     },
+    hearing::Hearing,
+    lighting::{
+        Lighting,
+        LIT_THRESHOLD,
+    },
+    map::Map,
     map_processing::MapProcessing,
     melee_combat::MeleeCombat,
+    MessageLog,
+    commands::{
+        Command,
+        perform,
+    },
     monster_ai::MonsterAi,
-    Rect,
-    SCREEN_HEIGHT,
-    View,
+    regeneration::Regenerate,
+    renderer::Renderer,
+    logging,
+    replay::{
+        checksum,
+        Recorder,
+    },
+    terrain::TerrainEffects,
+    tiles::TileType,
+    traps::TrapTrigger,
 };
 
 use wavebreaker_util::algorithms::fov::compute_fov;
 
+const SNEAK_TURNS: usize = 2;
+const CONSOLE_LINES: usize = 12;
+//a player who can't heal still stops resting eventually
+const MAX_REST_TURNS: usize = 500;
+const MAX_EXPLORE_STEPS: usize = 1000;
+
 #[derive(Clone, Copy, PartialEq)]
 pub enum State{
+    Dead,
     Paused,
     Running,
 }
 
-pub struct App<'a> {
+pub struct App {
     pub world: World,
     pub run_state: State,
-    pub font: FontCache<'a>,
+    pub recorder: Option<Recorder>,
+    pub turn: usize,
+    //the debug console with the latest log lines, drawn over the map
+    pub console: bool,
+    //the map cell under the mouse, if the frontend has one
+    pub hover: Option<(usize, usize)>,
 }
 
-impl<'a> App<'a> {
-    pub fn tick(&mut self, view: &mut View) {
+impl App {
+    pub fn tick(&mut self, renderer: &mut dyn Renderer) {
         if self.run_state == State::Running {
-            reveal_map(&self.world);
+            self.run_turn();
+        }
+
+        let positions = self.world.read_storage::<Position>();
+        let renderables = self.world.read_storage::<Renderable>();
+        let hidden = self.world.read_storage::<Hidden>();
+
+        let map = self.world.fetch::<Map>(); 
+        
+        renderer.clear();
+        draw_map(renderer, &map);
+        for (pos, render, _) in (&positions, &renderables, !&hidden).join() {
+            if map[(pos.x, pos.y)].visible {
+                draw_entity(renderer, pos, render);
+            }
+        }
+
+        renderer.draw_text(1, map.height - 2, "Hello", Color::RGB(255, 255, 255));
 
-            let mut monster_ai = MonsterAi { };
-            monster_ai.run_now(&self.world);
+        if let Some((x, y)) = self.hover {
+            let names = self.world.read_storage::<Name>();
+            let hovered: Vec<&str> = (&positions, &names, !&hidden)
+                .join()
+                .filter(|(pos, _, _)| (pos.x, pos.y) == (x, y) && map[(x, y)].visible)
+                .map(|(_, name, _)| name.name.as_str())
+                .collect();
 
-            let mut map_processing = MapProcessing { };
-            map_processing.run_now(&self.world);
+            if !hovered.is_empty() {
+                draw_tooltip(renderer, (x, y), &hovered.join(", "), map.width);
+            }
+        }
+
+        if self.console {
+            draw_console(renderer, map.width);
+        }
 
-            let mut melee_combat = MeleeCombat { };
-            melee_combat.run_now(&self.world);
+        renderer.present();
+    }
 
-            let mut damage = Damage { };
-            damage.run_now(&self.world);
+    pub fn run_turn(&mut self) {
+        let turns = if player_is_sneaking(&self.world) { SNEAK_TURNS } else { 1 };
+        for _ in 0..turns {
+            self.turn += 1;
+            logging::set_turn(self.turn);
+            self.run_systems();
+        }
 
-            cull_dead(&mut self.world);
+        if player_is_dead(&self.world) {
+            self.world.write_resource::<MessageLog>().push("You die...".to_string());
+            self.run_state = State::Dead;
+        } else {
             self.run_state = State::Paused;
         }
+    }
 
-        let positions = self.world.read_storage::<Position>();
-        let renderables = self.world.read_storage::<Renderable>();
+    //commands play out their turn straight away so the recorded checksum
+    //covers everything that followed from them
+    pub fn apply(&mut self, command: Command) {
+        if self.run_state == State::Dead { return }
 
-        let map = self.world.fetch::<Map>(); 
-        
-        view.clear();
-        draw_map(view, &mut self.font, &map);
-        for (pos, render) in (&positions, &renderables).join() {
-            if map[(pos.x, pos.y)].visible {
-                draw_entity(view, &mut self.font, pos, render);
+        if command == Command::Rest {
+            self.rest();
+        } else if command == Command::Explore {
+            self.explore();
+        } else if let Command::Travel(x, y) = command {
+            self.travel((x, y));
+        } else if perform(command, &self.world) {
+            self.run_turn();
+        }
+
+        if let Some(recorder) = &mut self.recorder {
+            recorder.record(command, checksum(&self.world));
+        }
+    }
+
+    //waits until the player is back to full health, stopping as soon as
+    //something happens: a hostile comes into view or a message arrives
+    fn rest(&mut self) {
+        if hostile_in_view(&self.world) {
+            self.world.write_resource::<MessageLog>().push("You can't rest with enemies nearby.".to_string());
+            return;
+        }
+
+        if !player_is_hurt(&self.world) {
+            self.world.write_resource::<MessageLog>().push("You're already at full health.".to_string());
+            return;
+        }
+
+        for _ in 0..MAX_REST_TURNS {
+            if !player_is_hurt(&self.world) { break }
+
+            let messages = self.world.fetch::<MessageLog>().count();
+            self.run_turn();
+
+            if self.run_state == State::Dead
+                || self.world.fetch::<MessageLog>().count() != messages
+                || hostile_in_view(&self.world)
+            {
+                break;
+            }
+        }
+    }
+
+    //walks toward the nearest unrevealed tile until there's none left, or
+    //until a hostile or a new item comes into view or the player gets hurt
+    fn explore(&mut self) {
+        if hostile_in_view(&self.world) {
+            self.world.write_resource::<MessageLog>().push("You can't explore with enemies nearby.".to_string());
+            return;
+        }
+
+        let seen_items = explore::visible_items(&self.world);
+
+        for _ in 0..MAX_EXPLORE_STEPS {
+            let (d_x, d_y) = match explore::next_step(&self.world) {
+                Some(step) => step,
+                None => {
+                    self.world.write_resource::<MessageLog>().push("There's nothing left to explore.".to_string());
+                    break;
+                },
+            };
+
+            let hp = player_hp(&self.world);
+            let messages = self.world.fetch::<MessageLog>().count();
+
+            if !perform(Command::Move(d_x, d_y), &self.world) { break }
+            self.run_turn();
+
+            if self.run_state == State::Dead
+                || player_hp(&self.world) < hp
+                || self.world.fetch::<MessageLog>().count() != messages
+                || hostile_in_view(&self.world)
+                || explore::visible_items(&self.world).iter().any(|item| !seen_items.contains(item))
+            {
+                break;
+            }
+        }
+    }
+
+    //follows a path to a revealed tile a turn at a time, stopping after any
+    //step that brings a hostile into view
+    fn travel(&mut self, goal: (usize, usize)) {
+        {
+            let map = self.world.fetch::<Map>();
+            if goal.0 >= map.width || goal.1 >= map.height || !map[goal].revealed { return }
+        }
+
+        let here = *self.world.fetch::<Position>();
+        if (here.x, here.y) == goal { return }
+
+        let mut steps = explore::route(&self.world, goal);
+        if steps.is_empty() {
+            self.world.write_resource::<MessageLog>().push("You can't find a way there.".to_string());
+            return;
+        }
+
+        while let Some((x, y)) = steps.pop() {
+            let here = *self.world.fetch::<Position>();
+            let (d_x, d_y) = (x as i16 - here.x as i16, y as i16 - here.y as i16);
+            let door = self.world.fetch::<Map>()[(x, y)].tile_type == TileType::ClosedDoor;
+
+            if !perform(Command::Move(d_x, d_y), &self.world) { break }
+            self.run_turn();
+
+            if self.run_state == State::Dead || hostile_in_view(&self.world) { break }
+
+            let there = *self.world.fetch::<Position>();
+            if (there.x, there.y) != (x, y) {
+                //walking into a door only opens it, stepping through takes
+                //another turn
+                if door && self.world.fetch::<Map>()[(x, y)].tile_type != TileType::ClosedDoor {
+                    steps.push((x, y));
+                    continue;
+                }
+
+                break;
             }
         }
+    }
+
+    fn run_systems(&mut self) {
+        let mut lighting = Lighting { };
+        lighting.run_now(&self.world);
+
+        reveal_map(&self.world);
+
+        let mut hearing = Hearing { };
+        hearing.run_now(&self.world);
+
+        let mut monster_ai = MonsterAi { };
+        monster_ai.run_now(&self.world);
 
-        view.draw_text(
-            &mut self.font, 
-            "Hello", 
-            Color::RGB(255, 255, 255), 
-            Color::RGBA(0, 0, 0, 0), 
-            (16, SCREEN_HEIGHT as i32 - 32), 
-            16
-        ).expect("Could not render text"); 
+        let mut map_processing = MapProcessing { };
+        map_processing.run_now(&self.world);
 
-        view.present();
+        let mut terrain_effects = TerrainEffects { };
+        terrain_effects.run_now(&self.world);
+
+        let mut trap_trigger = TrapTrigger { };
+        trap_trigger.run_now(&self.world);
+
+        let mut detection = Detection { };
+        detection.run_now(&self.world);
+
+        let mut regenerate = Regenerate { };
+        regenerate.run_now(&self.world);
+
+        let mut melee_combat = MeleeCombat { };
+        melee_combat.run_now(&self.world);
+
+        let mut damage = Damage { };
+        damage.run_now(&self.world);
+
+        cull_dead(&mut self.world);
     }
 }
 
+fn player_is_dead(world: &World) -> bool {
+    let players = world.read_storage::<Player>();
+    let combat_stats = world.read_storage::<CombatStats>();
+
+    (&players, &combat_stats).join().any(|(_, stats)| stats.hp < 1)
+}
+
+fn player_hp(world: &World) -> usize {
+    let players = world.read_storage::<Player>();
+    let combat_stats = world.read_storage::<CombatStats>();
+
+    (&players, &combat_stats).join().map(|(_, stats)| stats.hp).next().unwrap_or(0)
+}
+
+fn player_is_hurt(world: &World) -> bool {
+    let players = world.read_storage::<Player>();
+    let combat_stats = world.read_storage::<CombatStats>();
+
+    (&players, &combat_stats).join().any(|(_, stats)| stats.hp < stats.max_hp)
+}
+
+//any hostile the player can see, so long-running commands know to stop
+pub fn hostile_in_view(world: &World) -> bool {
+    let entities = world.entities();
+    let players = world.read_storage::<Player>();
+    let positions = world.read_storage::<Position>();
+    let combat_stats = world.read_storage::<CombatStats>();
+    let hidden = world.read_storage::<Hidden>();
+    let factions = world.read_storage::<Faction>();
+    let faction_table = world.fetch::<FactionTable>();
+    let map = world.fetch::<Map>();
+
+    let player = match (&entities, &players).join().next() {
+        Some((player, _)) => player,
+        None => return false,
+    };
+
+    (&entities, &positions, &combat_stats, !&hidden).join().any(|(entity, position, _, _)| {
+        map[(position.x, position.y)].visible
+            && reaction_between(&factions, &faction_table, player, entity) == Reaction::Hostile
+    })
+}
+
+fn player_is_sneaking(world: &World) -> bool {
+    let players = world.read_storage::<Player>();
+    let sneaking = world.read_storage::<Sneaking>();
+
+    (&players, &sneaking).join().next().is_some()
+}
+
 fn cull_dead(world: &mut World) {
     let mut dead : Vec<Entity> = Vec::new();
     {
         let combat_stats = world.read_storage::<CombatStats>();
+        let players = world.read_storage::<Player>();
         let entities = world.entities();
-        for (entity, stats) in (&entities, &combat_stats).join() {
+        for (entity, stats, _) in (&entities, &combat_stats, !&players).join() {
             if stats.hp < 1 { dead.push(entity); }
         }
     }
@@ -94,68 +348,83 @@ fn cull_dead(world: &mut World) {
     }
 }
 pub fn draw_entity(
-    view: &mut View, 
-    font: &mut FontCache, 
+    renderer: &mut dyn Renderer, 
     position: &Position, 
     renderable: &Renderable
 ) {
-    view.draw_glyph(
-        font, 
+    renderer.draw_glyph(
+        position.x, 
+        position.y, 
         renderable.glyph, 
         renderable.color, 
-        Color::RGB(0, 0, 0),
-        Rect::new(
-            (position.x as u32 * CELL_WIDTH) as i32, 
-            (position.y as u32 * CELL_HEIGHT) as i32, 
-            CELL_WIDTH, 
-            CELL_HEIGHT
-        )
-    ).expect("Could not render entity");
+        Color::RGB(0, 0, 0)
+    );
+}
+
+fn draw_console(renderer: &mut dyn Renderer, width: usize) {
+    let lines = logging::recent(CONSOLE_LINES);
+
+    for y in 0..CONSOLE_LINES {
+        for x in 0..width {
+            renderer.draw_glyph(x, y, ' ', Color::RGB(0, 0, 0), Color::RGB(20, 20, 40));
+        }
+    }
+
+    for (y, line) in lines.iter().enumerate() {
+        let line: String = line.chars().take(width - 2).collect();
+        renderer.draw_text(1, y, &line, Color::RGB(200, 200, 200));
+    }
+}
+
+//next to the hovered cell, or to its left when it would run off the map
+fn draw_tooltip(renderer: &mut dyn Renderer, (x, y): (usize, usize), text: &str, width: usize) {
+    let length = text.chars().count() + 2;
+    let left = if x + 1 + length <= width { x + 1 } else { x.saturating_sub(length) };
+
+    for offset in 0..length {
+        renderer.draw_glyph(left + offset, y, ' ', Color::RGB(0, 0, 0), Color::RGB(40, 40, 40));
+    }
+
+    renderer.draw_text(left + 1, y, text, Color::RGB(255, 255, 255));
 }
 
-pub fn draw_map(view: &mut View, font: &mut FontCache, map: &Map) {
+pub fn draw_map(renderer: &mut dyn Renderer, map: &Map) {
     for (idx, tile) in map.tiles.iter().enumerate() {
         let x = idx / map.height;
         let y = idx % map.height;
          
-        let color: Color;
-        let background = Color::RGB(0, 0, 0); 
+        let properties = tile.tile_type.properties();
+        let (mut foreground, mut background) = (properties.foreground, properties.background);
 
         let visible = map[(x,y)].visible;
 
+        //visible tiles are tinted by the light on them, remembered ones are
+        //drawn at half brightness
         if visible {
-            color = Color::RGB(255, 255, 255);
+            foreground = tint(foreground, tile.light);
+            background = tint(background, tile.light);
         } else {
-            color = Color::RGB(128, 128, 128);
+            foreground = (foreground.0 / 2, foreground.1 / 2, foreground.2 / 2);
+            background = (background.0 / 2, background.1 / 2, background.2 / 2);
         }
 
         if visible || map[(x,y)].revealed {
-            let x = (x as u32 * CELL_WIDTH) as i32;
-            let y = (y as u32 * CELL_HEIGHT) as i32;
-            
-            match tile.tile_type {
-               TileType::Wall => { 
-                    view.draw_glyph(font, 
-                        '\u{2593}', 
-                        color,
-                        background,
-                        Rect::new(x, y, CELL_WIDTH, CELL_HEIGHT)
-                    ).expect("Could not render entity");
-                },
-
-                TileType::Floor => {
-                    view.draw_glyph(font, 
-                        '.', 
-                        color,
-                        background,
-                        Rect::new(x, y, CELL_WIDTH, CELL_HEIGHT)
-                    ).expect("Could not render entity");
-                },
-            }
+            renderer.draw_glyph(x, 
+                y, 
+                properties.glyph, 
+                Color::RGB(foreground.0, foreground.1, foreground.2),
+                Color::RGB(background.0, background.1, background.2)
+            );
         }
     }
 }
 
+fn tint((r, g, b): (u8, u8, u8), (light_r, light_g, light_b): (f64, f64, f64)) -> (u8, u8, u8) {
+    let scale = |channel: u8, light: f64| (channel as f64 * (0.25 + light).min(1.0)) as u8;
+
+    (scale(r, light_r), scale(g, light_g), scale(b, light_b))
+}
+
 fn reveal_map(world: &World) {
     let players = world.read_storage::<Player>();
     let mut viewsheds = world.write_storage::<Viewshed>();
@@ -170,17 +439,22 @@ fn reveal_map(world: &World) {
             .expect("No viewshed for player");
 
     if viewshed.dirty {
-        for mut tile in &mut map.tiles {
-            tile.visible = false;
-        }
-
         viewshed.visible_tiles = compute_fov(
             (position.x, position.y), 
             &*map, 
             viewshed.range
         );
+    }
+
+    //lights move independently of the player, so what's visible is rechecked
+    //every turn even when the line of sight hasn't changed
+    for mut tile in &mut map.tiles {
+        tile.visible = false;
+    }
 
-        for &(x, y) in &viewshed.visible_tiles {
+    for &(x, y) in &viewshed.visible_tiles {
+        let light = map[(x, y)].light;
+        if light.0.max(light.1).max(light.2) > LIT_THRESHOLD {
             map[(x, y)].visible = true;
             map[(x, y)].revealed = true;
         }
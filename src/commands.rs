@@ -0,0 +1,76 @@
+use std::fmt;
+
+use specs::prelude::*;
+
+use crate::{
+    close_doors,
+    search,
+    toggle_sneak,
+    try_move_player,
+};
+
+//everything the player can do, independent of which key or frontend asked for it
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Command {
+    Move(i16, i16),
+    Wait,
+    Rest,
+    Explore,
+    Travel(usize, usize),
+    CloseDoors,
+    Search,
+    ToggleSneak,
+}
+
+impl Command {
+    pub fn parse(text: &str) -> Option<Command> {
+        let fields: Vec<&str> = text.split_whitespace().collect();
+
+        match fields.as_slice() {
+            ["move", d_x, d_y] => Some(Command::Move(d_x.parse().ok()?, d_y.parse().ok()?)),
+            ["wait"] => Some(Command::Wait),
+            ["rest"] => Some(Command::Rest),
+            ["explore"] => Some(Command::Explore),
+            ["travel", x, y] => Some(Command::Travel(x.parse().ok()?, y.parse().ok()?)),
+            ["close_doors"] => Some(Command::CloseDoors),
+            ["search"] => Some(Command::Search),
+            ["sneak"] => Some(Command::ToggleSneak),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Command::Move(d_x, d_y) => write!(f, "move {} {}", d_x, d_y),
+            Command::Wait => write!(f, "wait"),
+            Command::Rest => write!(f, "rest"),
+            Command::Explore => write!(f, "explore"),
+            Command::Travel(x, y) => write!(f, "travel {} {}", x, y),
+            Command::CloseDoors => write!(f, "close_doors"),
+            Command::Search => write!(f, "search"),
+            Command::ToggleSneak => write!(f, "sneak"),
+        }
+    }
+}
+
+//true if the command used up the player's turn
+pub fn perform(command: Command, world: &World) -> bool {
+    match command {
+        Command::Move(d_x, d_y) => try_move_player(d_x, d_y, world),
+        Command::Wait => true,
+        //resting, exploring and travelling are runs of turns rather than one,
+        //App::apply plays them out
+        Command::Rest | Command::Explore | Command::Travel(..) => false,
+        Command::CloseDoors => close_doors(world),
+        Command::Search => {
+            search(world);
+            true
+        },
+        Command::ToggleSneak => {
+            toggle_sneak(world);
+            false
+        },
+    }
+}
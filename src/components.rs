@@ -6,6 +6,11 @@ use crate::Color;
 #[derive(Component)]
 pub struct BlocksTile;
 
+#[derive(Component)]
+pub struct Companion {
+    pub leash: usize,
+}
+
 #[derive(Component, Debug)]
 pub struct CombatStats {
     pub max_hp: usize,
@@ -14,6 +19,14 @@ pub struct CombatStats {
     pub power: usize 
 }
 
+#[derive(Component)]
+pub struct Faction {
+    pub name: String,
+}
+
+#[derive(Component)]
+pub struct Hidden;
+
 #[derive(Component)]
 pub struct IncomingDamage {
     pub damage: Vec<usize>,
@@ -34,6 +47,29 @@ impl IncomingDamage {
     }
 }
 
+#[derive(Component)]
+pub struct LightSource {
+    pub radius: usize,
+    pub color: Color,
+    pub intensity: f64,
+}
+
+#[derive(Component)]
+pub struct Memory {
+    pub last_seen: Option<Position>,
+    pub search_turns: usize,
+}
+
+#[derive(Component)]
+pub struct InBackpack {
+    pub owner: Entity,
+}
+
+#[derive(Component)]
+pub struct Key {
+    pub lock: usize,
+}
+
 #[derive(Component)]
 pub struct Monster;
 
@@ -42,11 +78,28 @@ pub struct MeleeAttack {
     pub target: Entity,
 }
 
+#[derive(Component)]
+pub struct Morale {
+    pub threshold: usize,
+    pub fleeing: bool,
+}
+
 #[derive(Component)]
 pub struct Name {
     pub name: String,
 }
 
+#[derive(Component)]
+pub struct Path {
+    pub goal: (usize, usize),
+    pub steps: Vec<(usize, usize)>,
+}
+
+#[derive(Component)]
+pub struct Perception {
+    pub score: usize,
+}
+
 #[derive(Component)]
 pub struct Player;
 
@@ -56,12 +109,49 @@ pub struct Position {
     pub y: usize,
 }
 
+impl Position {
+    pub fn distance(&self, other: &Position) -> usize {
+        let x_distance = if self.x > other.x { self.x - other.x } else { other.x - self.x };
+        let y_distance = if self.y > other.y { self.y - other.y } else { other.y - self.y };
+
+        x_distance.max(y_distance)
+    }
+}
+
+//one hit point back every `turns_per_hp` turns spent below full health
+#[derive(Component)]
+pub struct Regeneration {
+    pub turns_per_hp: usize,
+    pub progress: usize,
+}
+
 #[derive(Component)]
 pub struct Renderable {
     pub glyph: char,
     pub color: Color,
 }
 
+#[derive(Component)]
+pub struct Searching;
+
+#[derive(Component)]
+pub struct Sneaking;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TrapKind {
+    AlarmPlate,
+    GasVent,
+    SpikePit,
+    Teleporter,
+}
+
+#[derive(Component)]
+pub struct Trap {
+    pub kind: TrapKind,
+    //whoever was standing on it last turn, so only stepping on it springs it
+    pub occupants: Vec<Entity>,
+}
+
 #[derive(Component)]
 pub struct Viewshed {
     pub visible_tiles: Vec<(usize, usize)>,
@@ -0,0 +1,266 @@
+use std::{
+    fs,
+    path::Path,
+};
+
+use crate::{
+    logging::{
+        self,
+        Level,
+    },
+    map::{
+        Generator,
+        Movement,
+    },
+};
+
+pub const DEFAULT_CONFIG_FILE: &str = "wavebreak.toml";
+pub const DEFAULT_LOG_FILE: &str = "log";
+//the smallest room that still has an interior to decorate and spawn into
+pub const MIN_ROOM_SIZE: usize = 3;
+
+//arguments that switch modes rather than change a setting, they take no value
+const MODE_FLAGS: [&str; 5] = [
+    "--frame-dump",
+    "--headless",
+    "--mapgen-dump",
+    "--mapgen-viz",
+    "--terminal",
+];
+
+pub struct Config {
+    pub seed: Option<usize>,
+    pub screen_width: u32,
+    pub screen_height: u32,
+    pub cell_width: u32,
+    pub cell_height: u32,
+    pub map_width: Option<usize>,
+    pub map_height: Option<usize>,
+    pub max_rooms: usize,
+    pub room_size: (usize, usize),
+    pub generator: Generator,
+    pub movement: Movement,
+    pub font: String,
+    pub log_file: String,
+    pub log_level: Level,
+    pub log_filters: Vec<(String, Level)>,
+    pub replay_file: String,
+    pub keymaps: Vec<String>,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            seed: None,
+            screen_width: 1920,
+            screen_height: 1024,
+            cell_width: 12,
+            cell_height: 20,
+            map_width: None,
+            map_height: None,
+            max_rooms: 10,
+            room_size: (5, 10),
+            generator: Generator::Rooms,
+            movement: Movement::EightWay,
+            font: "assets/InputMono-Regular.ttf".to_string(),
+            log_file: DEFAULT_LOG_FILE.to_string(),
+            log_level: Level::Info,
+            log_filters: Vec::new(),
+            replay_file: "replay".to_string(),
+            keymaps: vec![
+                "assets/keymaps/common.txt".to_string(),
+                "assets/keymaps/vi.txt".to_string(),
+                "assets/keymaps/numpad.txt".to_string(),
+            ],
+        }
+    }
+}
+
+impl Config {
+    //defaults, then the config file, then the command line
+    pub fn load(args: &[String]) -> Result<Config, String> {
+        let mut config = Config::default();
+
+        let path = match args.iter().position(|arg| arg == "--config") {
+            Some(flag) => Some(args.get(flag + 1).ok_or("--config needs a path")?.as_str()),
+            None if Path::new(DEFAULT_CONFIG_FILE).exists() => Some(DEFAULT_CONFIG_FILE),
+            None => None,
+        };
+
+        if let Some(path) = path {
+            config.read_file(path)?;
+        }
+
+        config.read_args(args)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.room_size.0 < MIN_ROOM_SIZE {
+            return Err(format!("min_room_size must be at least {}", MIN_ROOM_SIZE));
+        }
+
+        if self.room_size.0 >= self.room_size.1 {
+            return Err("min_room_size must be smaller than max_room_size".to_string());
+        }
+
+        if self.cell_width == 0 || self.cell_height == 0 {
+            return Err("cell_width and cell_height can't be zero".to_string());
+        }
+
+        if self.max_rooms == 0 {
+            return Err("max_rooms must be at least 1".to_string());
+        }
+
+        //the largest room and the wall around it have to fit on the map
+        let (map_width, map_height) = self.map_size();
+        if map_width < self.room_size.1 + 2 || map_height < self.room_size.1 + 2 {
+            return Err(format!(
+                "a {}x{} map is too small for rooms of up to {} tiles",
+                map_width,
+                map_height,
+                self.room_size.1
+            ));
+        }
+
+        Ok(())
+    }
+
+    //a flat subset of TOML: `key = value` lines, strings quoted, `#` comments
+    fn read_file(&mut self, path: &str) -> Result<(), String> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| format!("Could not read config {}: {}", path, e))?;
+
+        for (line_number, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') { continue }
+
+            let split = line.find('=')
+                .ok_or(format!("{}:{}: expected `key = value`", path, line_number + 1))?;
+            let key = line[..split].trim();
+            let value = parse_value(line[split + 1..].trim())
+                .ok_or(format!("{}:{}: could not parse value", path, line_number + 1))?;
+
+            self.set(key, &value).map_err(|e| format!("{}:{}: {}", path, line_number + 1, e))?;
+        }
+
+        Ok(())
+    }
+
+    //`--some-setting value` sets `some_setting`
+    fn read_args(&mut self, args: &[String]) -> Result<(), String> {
+        let mut args = args.iter().skip(1);
+
+        while let Some(arg) = args.next() {
+            if MODE_FLAGS.contains(&arg.as_str()) { continue }
+
+            let key = match arg.strip_prefix("--") {
+                Some(key) => key.replace('-', "_"),
+                None => return Err(format!("Unexpected argument {}", arg)),
+            };
+
+            let value = args.next().ok_or(format!("{} needs a value", arg))?;
+
+            //these name files for modes rather than settings
+            if key == "config" || key == "replay" { continue }
+
+            self.set(&key, value)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        let number = || value.parse::<usize>().map_err(|_| format!("{} must be a number", key));
+
+        match key {
+            "seed" => self.seed = Some(number()?),
+            "screen_width" => self.screen_width = number()? as u32,
+            "screen_height" => self.screen_height = number()? as u32,
+            "cell_width" => self.cell_width = number()? as u32,
+            "cell_height" => self.cell_height = number()? as u32,
+            "map_width" => self.map_width = Some(number()?),
+            "map_height" => self.map_height = Some(number()?),
+            "max_rooms" => self.max_rooms = number()?,
+            "min_room_size" => self.room_size.0 = number()?,
+            "max_room_size" => self.room_size.1 = number()?,
+            "generator" => {
+                self.generator = Generator::parse(value)
+                    .ok_or(format!("unknown generator {}", value))?;
+            },
+            "wfc_sample" => self.generator = Generator::WfcSample(value.to_string()),
+            "movement" => {
+                self.movement = Movement::parse(value)
+                    .ok_or(format!("unknown movement {}", value))?;
+            },
+            "font" => self.font = value.to_string(),
+            "log_file" => self.log_file = value.to_string(),
+            "log_level" => {
+                self.log_level = Level::parse(value)
+                    .ok_or(format!("unknown log level {}", value))?;
+            },
+            //`map=debug,wfc=warn`, overriding log_level for those modules
+            "log_filters" => {
+                self.log_filters = logging::parse_filters(value)
+                    .ok_or(format!("could not parse log filters {}", value))?;
+            },
+            "replay_file" => self.replay_file = value.to_string(),
+            "keymaps" => self.keymaps = value.split(',').map(|path| path.trim().to_string()).collect(),
+            other => return Err(format!("unknown setting {}", other)),
+        }
+
+        Ok(())
+    }
+
+    //without an explicit size the map fills the window
+    pub fn map_size(&self) -> (usize, usize) {
+        (
+            self.map_width.unwrap_or((self.screen_width / self.cell_width) as usize),
+            self.map_height.unwrap_or((self.screen_height / self.cell_height) as usize),
+        )
+    }
+
+    //everything a replay needs to build the same level again
+    pub fn generation_settings(&self, seed: usize) -> Vec<(&'static str, String)> {
+        let (map_width, map_height) = self.map_size();
+
+        vec![
+            ("seed", seed.to_string()),
+            ("map_width", map_width.to_string()),
+            ("map_height", map_height.to_string()),
+            ("max_rooms", self.max_rooms.to_string()),
+            ("min_room_size", self.room_size.0.to_string()),
+            ("max_room_size", self.room_size.1.to_string()),
+            ("generator", self.generator.to_string()),
+            ("movement", self.movement.to_string()),
+        ]
+    }
+}
+
+fn parse_value(text: &str) -> Option<String> {
+    if let Some(quoted) = text.strip_prefix('"') {
+        let mut value = String::new();
+        let mut chars = quoted.chars();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => {
+                    let rest = chars.as_str().trim();
+                    return if rest.is_empty() || rest.starts_with('#') { Some(value) } else { None };
+                },
+                '\\' => value.push(chars.next()?),
+                c => value.push(c),
+            }
+        }
+
+        return None;
+    }
+
+    let bare = match text.find('#') {
+        Some(comment) => text[..comment].trim(),
+        None => text,
+    };
+
+    if bare.is_empty() { None } else { Some(bare.to_string()) }
+}
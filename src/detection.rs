@@ -0,0 +1,87 @@
+use specs::prelude::*;
+use crate::{
+    components::*,
+    Map,
+    MessageLog,
+    SimpleRng,
+    tiles::TileType,
+};
+
+pub const SEARCH_BONUS: usize = 8;
+
+pub struct Detection {}
+
+impl<'a> System<'a> for Detection {
+    type SystemData = ( Entities<'a>,
+                        WriteExpect<'a, Map>,
+                        WriteExpect<'a, SimpleRng>,
+                        WriteExpect<'a, MessageLog>,
+                        ReadStorage<'a, Position>,
+                        ReadStorage<'a, Viewshed>,
+                        ReadStorage<'a, Perception>,
+                        ReadStorage<'a, Trap>,
+                        ReadStorage<'a, Name>,
+                        WriteStorage<'a, Hidden>,
+                        WriteStorage<'a, Searching> );
+
+    fn run(&mut self, data : Self::SystemData) {
+        let (
+            entities,
+            mut map,
+            mut rng,
+            mut messages,
+            positions,
+            viewsheds,
+            perceptions,
+            traps,
+            names,
+            mut hidden,
+            mut searching
+        ) = data;
+
+        let mut found: Vec<Entity> = Vec::new();
+        for (searcher, position, viewshed, perception) in 
+            (&entities, &positions, &viewsheds, &perceptions).join() 
+        {
+            //searching looks further and more carefully than just walking by
+            let is_searching = searching.remove(searcher).is_some();
+            let (range, score) = if is_searching {
+                (3, perception.score + SEARCH_BONUS)
+            } else {
+                (1, perception.score)
+            };
+
+            //secret doors only give themselves away to a deliberate search
+            if is_searching {
+                for x in position.x.saturating_sub(1) ..= position.x + 1 {
+                    for y in position.y.saturating_sub(1) ..= position.y + 1 {
+                        if x < map.width && y < map.height
+                            && map[(x, y)].tile_type == TileType::SecretDoor
+                            && rng.roll(0, 20) < score
+                        {
+                            map[(x, y)].tile_type = TileType::ClosedDoor;
+                            messages.push("You find a secret door!".to_string());
+                        }
+                    }
+                }
+            }
+
+            for (trap_entity, trap_position, _, _) in (&entities, &positions, &traps, &hidden).join() {
+                if position.distance(trap_position) <= range
+                    && viewshed.visible_tiles.contains(&(trap_position.x, trap_position.y))
+                    && rng.roll(0, 20) < score
+                {
+                    found.push(trap_entity);
+                }
+            }
+        }
+
+        for trap_entity in found {
+            if hidden.remove(trap_entity).is_some() {
+                if let Some(name) = names.get(trap_entity) {
+                    messages.push(format!("You spot a {}!", name.name));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,121 @@
+use std::{
+    cmp::Ordering,
+    collections::BinaryHeap,
+    marker::PhantomData,
+};
+
+use wavebreaker_util::data_structures::Graph;
+
+pub const FLEE_COEFFICIENT: f64 = -1.2;
+
+//graphs whose nodes can be numbered 0..node_count, so that a map's values
+//fit in a flat Vec rather than a hash map rebuilt every turn
+pub trait DenseGraph<N, T>: Graph<N, T> {
+    fn node_count(&self) -> usize;
+    fn node_index(&self, node: &N) -> usize;
+    fn node_at(&self, index: usize) -> N;
+}
+
+struct Frontier {
+    value: f64,
+    index: usize,
+}
+
+impl PartialEq for Frontier {
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value
+    }
+}
+
+impl Eq for Frontier { }
+
+impl Ord for Frontier {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.value.partial_cmp(&self.value).unwrap_or(Ordering::Equal)
+    }
+}
+
+impl PartialOrd for Frontier {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+pub struct DijkstraMap<N> {
+    values: Vec<f64>,
+    node: PhantomData<N>,
+}
+
+impl<N: Copy + PartialEq> DijkstraMap<N> {
+    pub fn new<T, G: DenseGraph<N, T>>(graph: &G, sources: &[(N, f64)]) -> DijkstraMap<N> {
+        let mut values = vec![f64::INFINITY; graph.node_count()];
+        let mut frontier = BinaryHeap::new();
+
+        for &(node, value) in sources {
+            let index = graph.node_index(&node);
+            if value < values[index] {
+                values[index] = value;
+                frontier.push(Frontier { value, index });
+            }
+        }
+
+        while let Some(Frontier { value, index }) = frontier.pop() {
+            if value > values[index] { continue }
+
+            let node = graph.node_at(index);
+            for neighbor in graph.neighbors(&node) {
+                let next_value = value + graph.cost(&node, &neighbor);
+                let next_index = graph.node_index(&neighbor);
+                if next_value < values[next_index] {
+                    values[next_index] = next_value;
+                    frontier.push(Frontier { value: next_value, index: next_index });
+                }
+            }
+        }
+
+        DijkstraMap { values, node: PhantomData }
+    }
+
+    //scaling every value and scanning again; with FLEE_COEFFICIENT this turns a
+    //distance map into one whose low points are far away without being dead ends
+    pub fn rescan<T, G: DenseGraph<N, T>>(&self, graph: &G, coefficient: f64) -> DijkstraMap<N> {
+        let sources: Vec<(N, f64)> = self.values
+            .iter()
+            .enumerate()
+            .filter(|(_, value)| value.is_finite())
+            .map(|(index, &value)| (graph.node_at(index), value * coefficient))
+            .collect();
+
+        DijkstraMap::new(graph, &sources)
+    }
+
+    pub fn get<T, G: DenseGraph<N, T>>(&self, graph: &G, node: &N) -> f64 {
+        self.values[graph.node_index(node)]
+    }
+
+    //nodes the graph won't step onto are never expanded, so their value is
+    //derived from their neighbors instead
+    pub fn value<T, G: DenseGraph<N, T>>(&self, graph: &G, node: &N) -> f64 {
+        let value = self.get(graph, node);
+        if value.is_finite() {
+            return value;
+        }
+
+        graph.neighbors(node)
+            .into_iter()
+            .filter(|neighbor| neighbor != node)
+            .map(|neighbor| self.get(graph, &neighbor) + graph.cost(node, &neighbor))
+            .fold(f64::INFINITY, f64::min)
+    }
+
+    pub fn downhill<T, G: DenseGraph<N, T>>(&self, graph: &G, node: &N, current: f64) -> Option<N> {
+        graph.neighbors(node)
+            .into_iter()
+            .filter(|neighbor| neighbor != node && self.get(graph, neighbor) < current)
+            .min_by(|a, b| {
+                self.get(graph, a)
+                    .partial_cmp(&self.get(graph, b))
+                    .unwrap_or(Ordering::Equal)
+            })
+    }
+}
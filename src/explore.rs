@@ -0,0 +1,126 @@
+use specs::prelude::*;
+
+use crate::{
+    components::*,
+    dijkstra_map::{
+        DenseGraph,
+        DijkstraMap,
+    },
+    map::{
+        Map,
+        Tile,
+    },
+    tiles::TileType,
+};
+
+use wavebreaker_util::{
+    algorithms::pathfinding::find_path,
+    data_structures::Graph,
+};
+
+//the map as the player can walk it: closed doors block monsters' paths but
+//the player opens them by walking into them, unless they're locked
+struct PlayerGraph<'a> {
+    map: &'a Map,
+}
+
+impl<'a> PlayerGraph<'a> {
+    fn is_unlocked_door(&self, tile: (usize, usize)) -> bool {
+        self.map[tile].tile_type == TileType::ClosedDoor && self.map[tile].lock.is_none()
+    }
+}
+
+impl<'a> Graph<(usize, usize), Tile> for PlayerGraph<'a> {
+    fn neighbors(&self, tile: &(usize, usize)) -> Vec<(usize, usize)> {
+        let mut neighbors = self.map.neighbors(tile);
+
+        for d_x in -1..=1 {
+            for d_y in -1..=1 {
+                if d_x != 0 && d_y != 0 && !self.map.allows_diagonal(*tile, (d_x, d_y)) { continue }
+
+                if let Some(door) = self.map.offset(*tile, (d_x, d_y)) {
+                    if self.is_unlocked_door(door) && !neighbors.contains(&door) {
+                        neighbors.push(door);
+                    }
+                }
+            }
+        }
+
+        neighbors
+    }
+
+    //unlike monsters, the player only steers around traps it has found. a
+    //closed door costs what stepping onto floor would, since walking into it
+    //opens it
+    fn cost(&self, start: &(usize, usize), end: &(usize, usize)) -> f64 {
+        let cost = self.map.cost_avoiding(start, end, |tile| tile.known_trap);
+        if cost.is_finite() || !self.is_unlocked_door(*end) { return cost }
+
+        let (d_x, d_y) = (start.0 as f64 - end.0 as f64, start.1 as f64 - end.1 as f64);
+        (d_x * d_x + d_y * d_y).sqrt() * TileType::Floor.properties().movement_cost
+    }
+
+    fn contains(&self, point: &(usize, usize)) -> bool {
+        self.map.contains(point)
+    }
+}
+
+impl<'a> DenseGraph<(usize, usize), Tile> for PlayerGraph<'a> {
+    fn node_count(&self) -> usize {
+        self.map.node_count()
+    }
+
+    fn node_index(&self, tile: &(usize, usize)) -> usize {
+        self.map.node_index(tile)
+    }
+
+    fn node_at(&self, index: usize) -> (usize, usize) {
+        self.map.node_at(index)
+    }
+}
+
+//the move that takes the player closer to the nearest unrevealed tile it can
+//reach, or nothing once everything reachable has been seen
+pub fn next_step(world: &World) -> Option<(i16, i16)> {
+    let map = world.fetch::<Map>();
+    let player = *world.fetch::<Position>();
+    let graph = PlayerGraph { map: &map };
+
+    let mut unrevealed = Vec::new();
+    for x in 0..map.width {
+        for y in 0..map.height {
+            if !map[(x, y)].revealed {
+                unrevealed.push(((x, y), 0.0));
+            }
+        }
+    }
+
+    let distances = DijkstraMap::new(&graph, &unrevealed);
+    let current = distances.get(&graph, &(player.x, player.y));
+    let (next_x, next_y) = distances.downhill(&graph, &(player.x, player.y), current)?;
+
+    Some((next_x as i16 - player.x as i16, next_y as i16 - player.y as i16))
+}
+
+//the steps from the player to `goal`, last step first, empty if there's no way
+pub fn route(world: &World, goal: (usize, usize)) -> Vec<(usize, usize)> {
+    let map = world.fetch::<Map>();
+    let player = *world.fetch::<Position>();
+
+    find_path::<Tile>((player.x, player.y), goal, &PlayerGraph { map: &map })
+}
+
+//items lying where the player can see them
+pub fn visible_items(world: &World) -> Vec<Entity> {
+    let entities = world.entities();
+    let keys = world.read_storage::<Key>();
+    let positions = world.read_storage::<Position>();
+    let hidden = world.read_storage::<Hidden>();
+    let map = world.fetch::<Map>();
+
+    (&entities, &keys, &positions, !&hidden)
+        .join()
+        .filter(|(_, _, position, _)| map[(position.x, position.y)].visible)
+        .map(|(entity, _, _, _)| entity)
+        .collect()
+}
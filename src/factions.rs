@@ -0,0 +1,71 @@
+use std::{
+    collections::HashMap,
+    fs,
+};
+
+use specs::prelude::*;
+
+use crate::components::Faction;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Reaction {
+    Hostile,
+    Neutral,
+    Allied,
+}
+
+pub struct FactionTable {
+    reactions: HashMap<(String, String), Reaction>,
+}
+
+impl FactionTable {
+    pub fn load(path: &str) -> Result<FactionTable, String> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| format!("Could not read faction table {}: {}", path, e))?;
+
+        let mut reactions = HashMap::new();
+        for (line_number, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') { continue }
+
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() != 3 {
+                return Err(format!("{}:{}: expected `faction other reaction`", path, line_number + 1));
+            }
+
+            let reaction = match fields[2] {
+                "hostile" => Reaction::Hostile,
+                "neutral" => Reaction::Neutral,
+                "allied" => Reaction::Allied,
+                other => return Err(format!("{}:{}: unknown reaction {}", path, line_number + 1, other)),
+            };
+
+            reactions.insert((fields[0].to_string(), fields[1].to_string()), reaction);
+        }
+
+        Ok(FactionTable { reactions })
+    }
+
+    pub fn reaction(&self, faction: &str, other: &str) -> Reaction {
+        if faction == other {
+            return Reaction::Allied;
+        }
+
+        match self.reactions.get(&(faction.to_string(), other.to_string())) {
+            Some(reaction) => *reaction,
+            None => Reaction::Neutral,
+        }
+    }
+}
+
+pub fn reaction_between(
+    factions: &ReadStorage<Faction>,
+    table: &FactionTable,
+    entity: Entity,
+    other: Entity
+) -> Reaction {
+    match (factions.get(entity), factions.get(other)) {
+        (Some(faction), Some(other_faction)) => table.reaction(&faction.name, &other_faction.name),
+        _ => Reaction::Neutral,
+    }
+}
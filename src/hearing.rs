@@ -0,0 +1,57 @@
+use specs::prelude::*;
+use crate::{
+    components::*,
+    monster_ai::SEARCH_TURNS,
+    Map,
+};
+
+pub const MOVE_NOISE: usize = 8;
+pub const SNEAK_NOISE: usize = 2;
+pub const COMBAT_NOISE: usize = 12;
+pub const DOOR_NOISE: usize = 10;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Noise {
+    pub x: usize,
+    pub y: usize,
+    pub volume: usize,
+}
+
+pub struct Hearing;
+
+impl<'a> System<'a> for Hearing {
+    type SystemData = (WriteExpect<'a, Vec<Noise>>,
+                       ReadExpect<'a, Map>,
+                       ReadStorage<'a, Position>,
+                       WriteStorage<'a, Memory>,
+                       ReadStorage<'a, Monster>,
+                       ReadStorage<'a, Companion>,
+                       ReadStorage<'a, Name>);
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (mut noises,
+             map,
+             positions,
+             mut memories,
+             monsters,
+             companions,
+             names) = data;
+
+        for noise in noises.iter() {
+            let loudness = map.propagate_noise((noise.x, noise.y), noise.volume);
+
+            //companions know the player's own noises for what they are
+            for (position, memory, _, _, name)
+                in (&positions, &mut memories, &monsters, !&companions, &names).join()
+            {
+                if loudness[(position.x * map.height) + position.y] > 0 {
+                    trace!("{} heard a noise at {:?}", name.name, noise);
+                    memory.last_seen = Some(Position { x: noise.x, y: noise.y });
+                    memory.search_turns = SEARCH_TURNS;
+                }
+            }
+        }
+
+        noises.clear();
+    }
+}
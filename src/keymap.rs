@@ -0,0 +1,87 @@
+use std::{
+    collections::HashMap,
+    fs,
+};
+
+use crate::{
+    commands::Command,
+    Keycode,
+};
+
+//what a key does: a game command, or something that only concerns the frontend
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Action {
+    Perform(Command),
+    ToggleConsole,
+    Quit,
+}
+
+impl Action {
+    fn parse(text: &str) -> Option<Action> {
+        match text {
+            "console" => Some(Action::ToggleConsole),
+            "quit" => Some(Action::Quit),
+            command => Command::parse(command).map(Action::Perform),
+        }
+    }
+}
+
+//keys are named the same way whichever frontend reads them: lowercase letters,
+//punctuation as itself, `left`/`right`/`up`/`down`, `escape` and `kp1`-`kp9`
+//for the number pad
+pub struct Keymap {
+    bindings: HashMap<String, Action>,
+}
+
+impl Keymap {
+    //later files override the bindings of earlier ones
+    pub fn load(paths: &[String]) -> Result<Keymap, String> {
+        let mut bindings = HashMap::new();
+
+        for path in paths {
+            let contents = fs::read_to_string(path)
+                .map_err(|e| format!("Could not read keymap {}: {}", path, e))?;
+
+            for (line_number, line) in contents.lines().enumerate() {
+                let line = line.trim();
+                if line.is_empty() || line.starts_with('#') { continue }
+
+                let split = line.find(char::is_whitespace)
+                    .ok_or(format!("{}:{}: expected `key command`", path, line_number + 1))?;
+                let action = Action::parse(line[split..].trim())
+                    .ok_or(format!("{}:{}: unknown command {}", path, line_number + 1, line[split..].trim()))?;
+
+                bindings.insert(line[..split].to_string(), action);
+            }
+        }
+
+        Ok(Keymap { bindings })
+    }
+
+    pub fn action(&self, key: &str) -> Option<Action> {
+        self.bindings.get(key).copied()
+    }
+}
+
+pub fn sdl_key_name(keycode: Keycode) -> Option<String> {
+    let name = match keycode {
+        Keycode::Left => "left",
+        Keycode::Right => "right",
+        Keycode::Up => "up",
+        Keycode::Down => "down",
+        Keycode::Escape => "escape",
+        Keycode::Kp1 => "kp1",
+        Keycode::Kp2 => "kp2",
+        Keycode::Kp3 => "kp3",
+        Keycode::Kp4 => "kp4",
+        Keycode::Kp5 => "kp5",
+        Keycode::Kp6 => "kp6",
+        Keycode::Kp7 => "kp7",
+        Keycode::Kp8 => "kp8",
+        Keycode::Kp9 => "kp9",
+        //printable keys are named by the character SDL gives them
+        other => return Some(other.name().to_lowercase()).filter(|name| name.chars().count() == 1),
+    };
+
+    Some(name.to_string())
+}
@@ -0,0 +1,40 @@
+use specs::prelude::*;
+use super::{LightSource, Map, Position};
+
+use wavebreaker_util::algorithms::fov::compute_fov;
+
+pub const AMBIENT_LIGHT: (f64, f64, f64) = (0.0, 0.0, 0.0);
+pub const LIT_THRESHOLD: f64 = 0.05;
+
+pub struct Lighting {}
+
+impl<'a> System<'a> for Lighting {
+    type SystemData = ( WriteExpect<'a, Map>,
+                        ReadStorage<'a, Position>,
+                        ReadStorage<'a, LightSource> );
+
+    fn run(&mut self, data : Self::SystemData) {
+        let (mut map, positions, light_sources) = data;
+
+        for tile in map.tiles.iter_mut() {
+            tile.light = AMBIENT_LIGHT;
+        }
+
+        for (position, light) in (&positions, &light_sources).join() {
+            let lit_tiles = compute_fov((position.x, position.y), &*map, light.radius);
+
+            for (x, y) in lit_tiles {
+                let d_x = x as f64 - position.x as f64;
+                let d_y = y as f64 - position.y as f64;
+                let distance = (d_x * d_x + d_y * d_y).sqrt();
+
+                let falloff = light.intensity * (1.0 - distance / (light.radius as f64 + 1.0)).max(0.0);
+
+                let tile = &mut map[(x, y)];
+                tile.light.0 += falloff * light.color.r as f64 / 255.0;
+                tile.light.1 += falloff * light.color.g as f64 / 255.0;
+                tile.light.2 += falloff * light.color.b as f64 / 255.0;
+            }
+        }
+    }
+}
@@ -0,0 +1,218 @@
+use std::{
+    backtrace::Backtrace,
+    collections::VecDeque,
+    fs::File,
+    io::{
+        BufWriter,
+        Write,
+    },
+    panic,
+    sync::{
+        atomic::{
+            AtomicUsize,
+            Ordering,
+        },
+        Mutex,
+        TryLockError,
+    },
+};
+
+//how many lines the debug console can scroll back through
+const RECENT_LINES: usize = 200;
+
+#[derive(Clone, Copy, PartialEq, PartialOrd, Debug)]
+pub enum Level {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl Level {
+    pub fn parse(text: &str) -> Option<Level> {
+        match text {
+            "error" => Some(Level::Error),
+            "warn" => Some(Level::Warn),
+            "info" => Some(Level::Info),
+            "debug" => Some(Level::Debug),
+            "trace" => Some(Level::Trace),
+            _ => None,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Level::Error => "ERROR",
+            Level::Warn => "WARN",
+            Level::Info => "INFO",
+            Level::Debug => "DEBUG",
+            Level::Trace => "TRACE",
+        }
+    }
+}
+
+//`module=level` pairs separated by commas, modules named without the crate
+pub fn parse_filters(text: &str) -> Option<Vec<(String, Level)>> {
+    text.split(',')
+        .filter(|filter| !filter.trim().is_empty())
+        .map(|filter| {
+            let split = filter.find('=')?;
+            let level = Level::parse(filter[split + 1..].trim())?;
+            Some((filter[..split].trim().to_string(), level))
+        })
+        .collect()
+}
+
+struct Logger {
+    out: BufWriter<File>,
+    level: Level,
+    filters: Vec<(String, Level)>,
+    recent: VecDeque<String>,
+}
+
+impl Logger {
+    //the most specific filter for the module wins, the default level otherwise
+    fn enabled(&self, module: &str, level: Level) -> bool {
+        let module = local_module(module);
+
+        let threshold = self.filters
+            .iter()
+            .filter(|(filter, _)| module == filter || module.starts_with(&format!("{}::", filter)))
+            .max_by_key(|(filter, _)| filter.len())
+            .map(|&(_, level)| level)
+            .unwrap_or(self.level);
+
+        level <= threshold
+    }
+}
+
+//module_path!() without the crate name, which is what filters are written
+//against; the crate root itself goes by main
+fn local_module(module: &str) -> &str {
+    module.split_once("::").map(|(_, local)| local).unwrap_or("main")
+}
+
+static LOGGER: Mutex<Option<Logger>> = Mutex::new(None);
+static SEED: AtomicUsize = AtomicUsize::new(0);
+static TURN: AtomicUsize = AtomicUsize::new(0);
+
+pub fn init(path: &str, level: Level, filters: Vec<(String, Level)>) -> Result<(), String> {
+    let file = File::create(path).map_err(|e| format!("Could not open log file {}: {}", path, e))?;
+
+    *LOGGER.lock().unwrap() = Some(Logger {
+        out: BufWriter::new(file),
+        level,
+        filters,
+        recent: VecDeque::new(),
+    });
+
+    panic::set_hook(Box::new(|panic_info| {
+        let report = format!(
+            "panic occurred: {}\nseed: {}\nturn: {}\nbacktrace:\n{}",
+            panic_info,
+            SEED.load(Ordering::Relaxed),
+            TURN.load(Ordering::Relaxed),
+            Backtrace::force_capture()
+        );
+
+        //a panic inside the logger itself still holds the lock, so the report
+        //goes to stderr rather than waiting on it forever
+        let mut guard = match LOGGER.try_lock() {
+            Ok(guard) => guard,
+            Err(TryLockError::Poisoned(poisoned)) => poisoned.into_inner(),
+            Err(TryLockError::WouldBlock) => {
+                eprintln!("{}", report);
+                return;
+            },
+        };
+
+        match guard.as_mut() {
+            Some(logger) => {
+                let _ = writeln!(logger.out, "{}", report);
+                let _ = logger.out.flush();
+            },
+            None => eprintln!("{}", report),
+        }
+    }));
+
+    Ok(())
+}
+
+pub fn set_seed(seed: usize) {
+    SEED.store(seed, Ordering::Relaxed);
+}
+
+pub fn set_turn(turn: usize) {
+    TURN.store(turn, Ordering::Relaxed);
+}
+
+//called through the error!, warn!, info!, debug! and trace! macros
+pub fn write(level: Level, module: &str, message: &str) {
+    let mut guard = LOGGER.lock().unwrap();
+    let logger = match guard.as_mut() {
+        Some(logger) => logger,
+        None => return,
+    };
+
+    if !logger.enabled(module, level) { return }
+
+    let line = format!(
+        "{:>5} {:<5} {}: {}",
+        TURN.load(Ordering::Relaxed),
+        level.name(),
+        local_module(module),
+        message
+    );
+
+    logger.out.write_all(line.as_bytes()).expect("Could not write to log file");
+    logger.out.write_all(b"\n").expect("Could not write to log file");
+
+    //errors are written out straight away in case they're the last thing logged
+    if level == Level::Error {
+        logger.out.flush().expect("Could not write to log file");
+    }
+
+    if logger.recent.len() == RECENT_LINES {
+        logger.recent.pop_front();
+    }
+    logger.recent.push_back(line);
+}
+
+pub fn flush() {
+    if let Some(logger) = LOGGER.lock().unwrap().as_mut() {
+        logger.out.flush().expect("Could not write to log file");
+    }
+}
+
+//the last `count` lines, oldest first
+pub fn recent(count: usize) -> Vec<String> {
+    match LOGGER.lock().unwrap().as_ref() {
+        Some(logger) => logger.recent
+            .iter()
+            .skip(logger.recent.len().saturating_sub(count))
+            .cloned()
+            .collect(),
+        None => Vec::new(),
+    }
+}
+
+macro_rules! error {
+    ($($arg:tt)*) => { $crate::logging::write($crate::logging::Level::Error, module_path!(), &format!($($arg)*)) }
+}
+
+macro_rules! warn {
+    ($($arg:tt)*) => { $crate::logging::write($crate::logging::Level::Warn, module_path!(), &format!($($arg)*)) }
+}
+
+macro_rules! info {
+    ($($arg:tt)*) => { $crate::logging::write($crate::logging::Level::Info, module_path!(), &format!($($arg)*)) }
+}
+
+macro_rules! debug {
+    ($($arg:tt)*) => { $crate::logging::write($crate::logging::Level::Debug, module_path!(), &format!($($arg)*)) }
+}
+
+macro_rules! trace {
+    ($($arg:tt)*) => { $crate::logging::write($crate::logging::Level::Trace, module_path!(), &format!($($arg)*)) }
+}
@@ -1,10 +1,6 @@
 use std::{
     convert::TryInto,
-    fs::OpenOptions,
-    io::{
-        Write,
-    },
-    panic,
+    env,
     time::{
         SystemTime,
         UNIX_EPOCH,
@@ -13,24 +9,94 @@ use std::{
 
 use specs::prelude::*;
 
+//first, so the logging macros are in scope for every module after it
+#[macro_use]
+mod logging;
+
 mod app;
 use app::{
     App,
     State,
 };
 
+mod commands;
+use commands::Command;
+
 mod components;
 use components::*;
 
+mod config;
+use config::Config;
+
 mod damage;
+mod detection;
+mod dijkstra_map;
+mod explore;
+mod factions;
+use factions::{
+    FactionTable,
+    Reaction,
+    reaction_between,
+};
+
+mod hearing;
+use hearing::{
+    DOOR_NOISE,
+    MOVE_NOISE,
+    Noise,
+    SNEAK_NOISE,
+};
+
+mod keymap;
+use keymap::{
+    Action,
+    Keymap,
+    sdl_key_name,
+};
+
+mod lighting;
+
 mod map;
 use map::{
+    Generator,
     Map,
 };
 
 mod map_processing;
+mod mapgen_viz;
 mod melee_combat;
+mod message_log;
+use message_log::MessageLog;
 mod monster_ai;
+mod prefab;
+mod regeneration;
+mod renderer;
+mod replay;
+use replay::{
+    Recorder,
+    Replay,
+};
+
+use renderer::{
+    SdlRenderer,
+    TextGrid,
+};
+use prefab::{
+    Legend,
+    Prefab,
+};
+
+#[cfg(test)]
+mod snapshot;
+mod spawner;
+mod terrain;
+#[cfg(feature = "terminal")]
+mod terminal;
+mod tiles;
+mod traps;
+mod wfc;
+use wfc::Constraints;
+use tiles::TileType;
 
 pub use wavebreaker_sdl2::{
     font::{
@@ -41,6 +107,7 @@ pub use wavebreaker_sdl2::{
         Color,
         Event,
         Keycode,
+        MouseButton,
         Rect,
         View,
     }
@@ -55,33 +122,30 @@ pub use wavebreaker_util::{
     data_structures::Queue,
 };
 
-const LOG_FILE: &str = "log";
-
-const SCREEN_WIDTH: u32 = 1920;
-const SCREEN_HEIGHT: u32 = 1024;
-const CELL_WIDTH: u32 = 12;
-const CELL_HEIGHT: u32 = 20;
-
-fn log(message: &str) {
-    let mut log = OpenOptions::new()
-        .append(true)
-        .create(true)
-        .open(LOG_FILE).expect("Could not open log file");
-    log.write_all(message.as_bytes()).expect("could not write to log file");
-    log.write(&['\n' as u8]).expect("could not write to log file");
-}
-
 fn try_move_player(d_x: i16, d_y: i16, world: &World) -> bool {
     let mut positions = world.write_storage::<Position>();
     let entities = world.entities();
     let players = world.write_storage::<Player>();
+    let sneaking = world.read_storage::<Sneaking>();
+    let factions = world.read_storage::<Faction>();
     let mut viewsheds = world.write_storage::<Viewshed>();
 
-    let map = world.fetch::<Map>();
+    let keys = world.read_storage::<Key>();
+
+    let mut map = world.fetch_mut::<Map>();
+    let faction_table = world.fetch::<FactionTable>();
+
+    let mut moved = false;
+    let mut swap_with: Option<(Entity, Position)> = None;
+    let mut picked_up: Vec<(Entity, Entity)> = Vec::new();
     
     for (entity, _, pos, viewshed) in 
         (&entities, &players, &mut positions, &mut viewsheds).join() 
     {
+        if d_x != 0 && d_y != 0 && !map.allows_diagonal((pos.x, pos.y), (d_x as i64, d_y as i64)) {
+            return false;
+        }
+
         let dest_x: Option<usize> = (pos.x as i16 + d_x).try_into().ok();
         let dest_y: Option<usize> = (pos.y as i16 + d_y).try_into().ok();
         
@@ -90,188 +154,473 @@ fn try_move_player(d_x: i16, d_y: i16, world: &World) -> bool {
             let dest_y = dest_y.unwrap();
 
             if dest_x < map.width && dest_y < map.height {
-                let tile = &map[(dest_x, dest_y)];
-                if !tile.blocked {
-                    //set player's position component
-                    pos.x = dest_x;
-                    pos.y = dest_y;
+                if map[(dest_x, dest_y)].tile_type == TileType::ClosedDoor {
+                    let opened = open_door(world, &mut map, entity, (dest_x, dest_y));
+                    if opened { viewshed.dirty = true; }
 
-                    //update player position resource
-                    let mut player_pos = world.write_resource::<Position>();
-                    player_pos.x = pos.x;
-                    player_pos.y = pos.y;
-
-                    viewshed.dirty = true;
+                    return opened;
+                }
 
-                    return true;
-                } else {
-                    let mut moved: bool = false;
+                let tile = &map[(dest_x, dest_y)];
+                if tile.blocked {
+                    let mut attacked: bool = false;
 
                     for potential_target in tile.entities.iter() {
                         let combat_stats = world.read_storage::<CombatStats>();
                         let mut melee_attacks = world.write_storage::<MeleeAttack>();
 
                         match combat_stats.get(*potential_target) {
-                            Some(target) => {
-                                melee_attacks.insert(
-                                    entity, 
-                                    MeleeAttack { target: *potential_target }
-                                ).expect("Could not add attack");
-                                moved = true; 
+                            Some(_) => {
+                                match reaction_between(&factions, &faction_table, entity, *potential_target) {
+                                    Reaction::Hostile => {
+                                        melee_attacks.insert(
+                                            entity, 
+                                            MeleeAttack { target: *potential_target }
+                                        ).expect("Could not add attack");
+                                        attacked = true; 
+                                    },
+
+                                    Reaction::Neutral | Reaction::Allied => {
+                                        swap_with = Some((*potential_target, *pos));
+                                    },
+                                }
                             },
 
                             None => { },
                         }
                     }
 
-                    return moved;
+                    if attacked { return true; }
+                    if swap_with.is_none() { return false; }
+                }
+
+                //set player's position component
+                pos.x = dest_x;
+                pos.y = dest_y;
+
+                //update player position resource
+                let mut player_pos = world.write_resource::<Position>();
+                player_pos.x = pos.x;
+                player_pos.y = pos.y;
+
+                viewshed.dirty = true;
+
+                let volume = if sneaking.contains(entity) { SNEAK_NOISE } else { MOVE_NOISE };
+                let mut noises = world.write_resource::<Vec<Noise>>();
+                noises.push(Noise { x: pos.x, y: pos.y, volume });
+
+                for item in tile.entities.iter() {
+                    if keys.contains(*item) {
+                        picked_up.push((*item, entity));
+                    }
+                }
+
+                moved = true;
+            }
+        }
+    }
+
+    //neutral and allied entities trade places with the player
+    if let Some((other, old_position)) = swap_with {
+        positions.insert(other, old_position).expect("Could not swap positions");
+        if let Some(viewshed) = viewsheds.get_mut(other) {
+            viewshed.dirty = true;
+        }
+    }
+
+    for (item, owner) in picked_up {
+        let mut backpacks = world.write_storage::<InBackpack>();
+        let names = world.read_storage::<Name>();
+        let mut messages = world.write_resource::<MessageLog>();
+
+        positions.remove(item);
+        backpacks.insert(item, InBackpack { owner }).expect("Could not pick up item");
+        if let Some(name) = names.get(item) {
+            messages.push(format!("You pick up the {}.", name.name));
+        }
+    }
+
+    moved
+}
+
+fn open_door(world: &World, map: &mut Map, opener: Entity, door: (usize, usize)) -> bool {
+    let keys = world.read_storage::<Key>();
+    let backpacks = world.read_storage::<InBackpack>();
+    let mut messages = world.write_resource::<MessageLog>();
+
+    if let Some(lock) = map[door].lock {
+        let has_key = (&keys, &backpacks)
+            .join()
+            .any(|(key, backpack)| key.lock == lock && backpack.owner == opener);
+
+        if !has_key {
+            messages.push("The door is locked.".to_string());
+            return false;
+        }
+
+        messages.push("You unlock the door.".to_string());
+        map[door].lock = None;
+    }
+
+    map[door].tile_type = TileType::OpenDoor;
+    map[door].blocked = false;
+
+    let mut noises = world.write_resource::<Vec<Noise>>();
+    noises.push(Noise { x: door.0, y: door.1, volume: DOOR_NOISE });
+
+    true
+}
+
+fn close_doors(world: &World) -> bool {
+    let players = world.read_storage::<Player>();
+    let positions = world.read_storage::<Position>();
+    let mut viewsheds = world.write_storage::<Viewshed>();
+    let mut messages = world.write_resource::<MessageLog>();
+    let mut noises = world.write_resource::<Vec<Noise>>();
+
+    let mut map = world.fetch_mut::<Map>();
+
+    let mut closed = false;
+    for (_, pos, viewshed) in (&players, &positions, &mut viewsheds).join() {
+        for x in pos.x.saturating_sub(1) ..= pos.x + 1 {
+            for y in pos.y.saturating_sub(1) ..= pos.y + 1 {
+                if x >= map.width || y >= map.height || (x, y) == (pos.x, pos.y) { continue }
+
+                let tile = &mut map[(x, y)];
+                if tile.tile_type == TileType::OpenDoor && tile.entities.is_empty() {
+                    tile.tile_type = TileType::ClosedDoor;
+                    tile.blocked = true;
+                    noises.push(Noise { x, y, volume: DOOR_NOISE });
+                    closed = true;
                 }
             }
         }
+
+        if closed {
+            viewshed.dirty = true;
+            messages.push("You close the door.".to_string());
+        }
     }
 
-    false
+    closed
 }
 
-fn main() -> Result<(), String> {
-    let _ = OpenOptions::new()
-        .write(true)
-        .create(true)
-        .truncate(true)
-        .open(LOG_FILE)
-        .expect("Could not open log file");
-
-    panic::set_hook(Box::new(|panic_info| {
-        let mut log = OpenOptions::new()
-            .append(true)
-            .create(true)
-            .open(LOG_FILE)
-            .expect("Could not open log file");
-        log.write_all(
-            format!("panic occurred: {:?}", panic_info)
-            .as_bytes()
-        ).expect("Error writing to log file");
-    }));
+fn toggle_sneak(world: &World) {
+    let entities = world.entities();
+    let players = world.read_storage::<Player>();
+    let mut sneaking = world.write_storage::<Sneaking>();
+    let mut messages = world.write_resource::<MessageLog>();
+
+    for (entity, _) in (&entities, &players).join() {
+        if sneaking.remove(entity).is_some() {
+            messages.push("You stop sneaking.".to_string());
+        } else {
+            sneaking.insert(entity, Sneaking).expect("Could not start sneaking");
+            messages.push("You begin sneaking.".to_string());
+        }
+    }
+}
 
-    let mut view = View::init("Wavebreaker", 
-        SCREEN_WIDTH, 
-        SCREEN_HEIGHT
-    ).expect("Could not initialize view"); 
+fn search(world: &World) {
+    let entities = world.entities();
+    let players = world.read_storage::<Player>();
+    let mut searching = world.write_storage::<Searching>();
 
-    let font_manager = FontManager::init(view.canvas())?;
-    let input_mono = font_manager.load("assets/InputMono-Regular.ttf")?;
+    for (entity, _) in (&entities, &players).join() {
+        searching.insert(entity, Searching).expect("Could not start searching");
+    }
+}
+
+//the map cell under a point in the window
+fn cell_at(x: i32, y: i32, (cell_width, cell_height): (u32, u32)) -> Option<(usize, usize)> {
+    if x < 0 || y < 0 { return None }
+
+    Some((x as usize / cell_width as usize, y as usize / cell_height as usize))
+}
+
+//the level for the given settings, along with the legend its layout is
+//written in
+fn generate_map(config: &Config, rng: &mut SimpleRng) -> Result<(Map, Legend), String> {
+    let (map_width, map_height) = config.map_size();
+    let mut map = Map::random_rooms(
+        map_width, 
+        map_height, 
+        config.max_rooms, config.room_size, 
+        rng
+    );
+
+    let legend = Legend::load("assets/prefabs/legend.txt")?;
 
+    if config.generator != Generator::Rooms {
+        let constraints = if let Generator::WfcSample(path) = &config.generator {
+            let sample = Prefab::load(path, &legend)?;
+            Constraints::learn(sample.width, sample.height, |x, y| {
+                legend.get(sample.glyph(x, y)).unwrap().tile_type
+            })
+        } else {
+            Constraints::learn(map.width, map.height, |x, y| map[(x, y)].tile_type)
+        };
+
+        match Map::wave_function_collapse(map.width, map.height, &constraints, config.max_rooms, rng) {
+            Some(collapsed) => map = collapsed,
+            None => warn!("Wave function collapse failed, keeping the room map"),
+        }
+    }
+
+    map.movement = config.movement;
+
+    let prefabs = Prefab::load_all("assets/prefabs", &legend)?;
+    map.stamp_prefabs(&prefabs, &legend, rng);
+
+    Ok((map, legend))
+}
+
+//a world with everything spawned into the level, ready for its first turn
+fn new_game(map: Map, mut rng: SimpleRng) -> Result<App, String> {
     let mut state = App { 
         world: World::new(),
         run_state: State::Running,
-        font: input_mono,
+        recorder: None,
+        turn: 0,
+        console: false,
+        hover: None,
     };
 
-    let now = SystemTime::now();
-    let timestamp = now
-        .duration_since(UNIX_EPOCH)
-        .expect("Time went backwards")
-        .as_secs();
-
-    let mut rng = SimpleRng::new(timestamp as usize);
-
     state.world.register::<BlocksTile>();
     state.world.register::<CombatStats>();
+    state.world.register::<Companion>();
+    state.world.register::<Faction>();
+    state.world.register::<Hidden>();
     state.world.register::<IncomingDamage>();
+    state.world.register::<InBackpack>();
+    state.world.register::<Key>();
+    state.world.register::<LightSource>();
+    state.world.register::<Memory>();
     state.world.register::<Monster>();
+    state.world.register::<Morale>();
     state.world.register::<MeleeAttack>();
     state.world.register::<Name>();
+    state.world.register::<Path>();
+    state.world.register::<Perception>();
     state.world.register::<Player>();
     state.world.register::<Position>();
+    state.world.register::<Regeneration>();
     state.world.register::<Renderable>();
+    state.world.register::<Searching>();
+    state.world.register::<Sneaking>();
+    state.world.register::<Trap>();
     state.world.register::<Viewshed>();
     
-    let map_width = SCREEN_WIDTH / CELL_WIDTH;
-    let map_height = SCREEN_HEIGHT / CELL_HEIGHT;
-    let map = Map::random_rooms(
-        map_width as usize, 
-        map_height as usize, 
-        10, (5, 10), 
-        &mut rng
-    );
+    //unlucky rolls can still leave a small map without a single room
+    let first_room = map.rooms.first().ok_or("The level was generated without any rooms")?;
     let player_position = Position { 
-        x: map.rooms[0].center().0, 
-        y: map.rooms[0].center().1 
+        x: first_room.center().0, 
+        y: first_room.center().1 
     };
     
-    state.world
-        .create_entity()
-        .with(Player)
-        .with(Name { name: "Player".to_string() })
-        .with(player_position)
-        .with(Renderable {
-            glyph: '@',
-            color: Color::RGB(0, 0, 255),
-        })
-        .with(Viewshed { visible_tiles: Vec::new(), range: 10, dirty: true })
-        .with(CombatStats {max_hp: 30, hp: 30, defense: 2, power: 5 })
-        .build();
-
-    for (idx, room) in map.rooms.iter().skip(1).enumerate() {
-        state.world
-            .create_entity()
-            .with(Name { name: format!("Goblin #{}", idx + 1) })
-            .with(Position { x: room.center().0, y: room.center().1 })
-            .with(Renderable {
-                glyph: 'g',
-                color: Color::RGB(255, 0, 0)
-            })
-            .with(Viewshed { visible_tiles: Vec::new(), range: 8, dirty: true })
-            .with(Monster)
-            .with(BlocksTile)
-            .with(CombatStats{ max_hp: 16, hp: 16, defense: 1, power: 4 })
-            .build();
+    spawner::player(&mut state.world, player_position);
+    spawner::dog(&mut state.world, Position { x: player_position.x + 1, y: player_position.y });
+
+    for (idx, room) in map.rooms.iter().enumerate().skip(1) {
+        if map.prefab_rooms.contains(&idx) { continue }
+
+        let position = Position { x: room.center().0, y: room.center().1 };
+        spawner::random_monster(&mut state.world, position, idx, &mut rng);
+    }
+
+    for (idx, room) in map.rooms.iter().enumerate().skip(1) {
+        if map.prefab_rooms.contains(&idx) { continue }
+
+        match rng.roll(0, 4) {
+            0 | 1 => {
+                spawner::torch(&mut state.world, Position { x: room.x2 - 1, y: room.y1 + 1 });
+            },
+
+            2 => {
+                spawner::glowing_fungus(&mut state.world, Position { x: room.x1 + 1, y: room.y2 - 1 });
+            },
+
+            _ => { },
+        }
+    }
+
+    for (idx, room) in map.rooms.iter().enumerate().skip(1) {
+        if map.prefab_rooms.contains(&idx) { continue }
+        if rng.roll(0, 3) != 0 { continue }
+
+        let position = Position { 
+            x: rng.roll(room.x1 + 1, room.x2), 
+            y: rng.roll(room.y1 + 1, room.y2) 
+        };
+        if (position.x, position.y) == room.center() { continue }
+
+        spawner::random_trap(&mut state.world, position, &mut rng);
+    }
+
+    for &((x, y), lock) in map.key_spawns.iter() {
+        spawner::key(&mut state.world, Position { x, y }, lock);
+    }
+
+    let first_prefab_spawn = map.rooms.len();
+    for (idx, ((x, y), name)) in map.spawns.iter().enumerate() {
+        spawner::named(&mut state.world, name, Position { x: *x, y: *y }, first_prefab_spawn + idx)?;
     }
 
     state.world.insert(map);  
     state.world.insert(player_position);
+    state.world.insert(rng);
+    state.world.insert(FactionTable::load("assets/factions.txt")?);
 
-    let messages = Queue::<String>::new(3);
+    let messages = MessageLog::new(3);
     state.world.insert(messages);
+    state.world.insert(Vec::<Noise>::new());
+
+    Ok(state)
+}
+
+//whichever way the game ends, buffered log lines make it to the file
+fn main() -> Result<(), String> {
+    let result = run();
+    if let Err(error) = &result {
+        error!("{}", error);
+    }
+
+    logging::flush();
+    result
+}
+
+fn run() -> Result<(), String> {
+    let args: Vec<String> = env::args().collect();
+    let mut config = Config::load(&args)?;
+
+    let replay = match args.iter().position(|arg| arg == "--replay") {
+        Some(flag) => Some(Replay::load(args.get(flag + 1).ok_or("--replay needs a path")?)?),
+        None => None,
+    };
+
+    //replays are played back in the window or checked headless, the terminal
+    //frontend only runs new games
+    if replay.is_some() && args.iter().any(|arg| arg == "--terminal") {
+        return Err("--replay can't be combined with --terminal".to_string());
+    }
+
+    //a replay brings the settings its level was generated from
+    if let Some(replay) = &replay {
+        for (key, value) in replay.settings.iter() {
+            config.set(key, value)?;
+        }
+        config.validate()?;
+    }
+
+    logging::init(&config.log_file, config.log_level, config.log_filters.clone())?;
+
+    let seed = config.seed.unwrap_or_else(|| {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("Time went backwards")
+            .as_secs() as usize
+    });
+
+    logging::set_seed(seed);
+    info!("Seed: {}", seed);
+    let mut rng = SimpleRng::new(seed);
+    let (map, legend) = generate_map(&config, &mut rng)?;
+
+    //a headless dump of every generation step, for diffing against known output
+    if args.iter().any(|arg| arg == "--mapgen-dump") {
+        print!("{}", mapgen_viz::dump(&map, &legend));
+        return Ok(());
+    }
+
+    let mut state = new_game(map, rng)?;
+
+    //one turn rendered into a text grid instead of a window
+    if args.iter().any(|arg| arg == "--frame-dump") {
+        let (width, height) = {
+            let map = state.world.fetch::<Map>();
+            (map.width, map.height)
+        };
+
+        let mut grid = TextGrid::new(width, height);
+        state.tick(&mut grid);
+        print!("{}", grid);
+        return Ok(());
+    }
+
+    //--headless checks the replay without drawing anything, otherwise it's
+    //played back in the window
+    if let Some(replay) = &replay {
+        if args.iter().any(|arg| arg == "--headless") {
+            replay::verify(&mut state, replay)?;
+            println!("Replay of {} commands matched", replay.steps.len());
+            return Ok(());
+        }
+    } else {
+        state.recorder = Some(Recorder::create(&config.replay_file, &config.generation_settings(seed))?);
+    }
+
+    let keymap = Keymap::load(&config.keymaps)?;
+
+    if args.iter().any(|arg| arg == "--terminal") {
+        #[cfg(feature = "terminal")]
+        return terminal::run(&mut state, &keymap);
+
+        #[cfg(not(feature = "terminal"))]
+        return Err("--terminal needs a build with the terminal feature".to_string());
+    }
+
+    let mut view = View::init("Wavebreaker", 
+        config.screen_width, 
+        config.screen_height
+    ).expect("Could not initialize view"); 
+
+    let font_manager = FontManager::init(view.canvas())?;
+    let mut input_mono = font_manager.load(&config.font)?;
+    let cell_size = (config.cell_width, config.cell_height);
+
+    if args.iter().any(|arg| arg == "--mapgen-viz") {
+        return mapgen_viz::run(&mut view, &mut input_mono, cell_size, &state.world.fetch::<Map>());
+    }
+
+    if let Some(replay) = &replay {
+        return replay::play(&mut state, &mut view, &mut input_mono, cell_size, replay);
+    }
 
     let mut event_pump = view.event_pump()?;
 
     let mut quit = false;
     while !quit {
-        state.tick(&mut view);
+        state.tick(&mut SdlRenderer { view: &mut view, font: &mut input_mono, cell_size });
         
         for event in event_pump.poll_iter() {
-            match event {
-                Event::Quit {..} |
-                Event::KeyDown { keycode: Some(Keycode::Escape), .. } => {
+            let keycode = match event {
+                Event::Quit {..} => {
                     quit = true;
+                    continue;
                 },
 
-                Event::KeyDown { keycode: Some(Keycode::Left), .. } => {
-                    if try_move_player(-1, 0, &state.world) {
-                        state.run_state = State::Running;
-                    }
-                }
-
-                Event::KeyDown { keycode: Some(Keycode::Right), .. } => {
-                    if try_move_player(1, 0, &state.world) {
-                        state.run_state = State::Running;
-                    }
-                }
+                Event::MouseMotion { x, y, .. } => {
+                    state.hover = cell_at(x, y, cell_size);
+                    continue;
+                },
 
-                Event::KeyDown { keycode: Some(Keycode::Up), .. } => {
-                    if try_move_player(0, -1, &state.world) {
-                        state.run_state = State::Running;
+                Event::MouseButtonDown { mouse_btn: MouseButton::Left, x, y, .. } => {
+                    if let Some((x, y)) = cell_at(x, y, cell_size) {
+                        state.apply(Command::Travel(x, y));
                     }
-                }
+                    continue;
+                },
 
-                Event::KeyDown { keycode: Some(Keycode::Down), .. } => {
-                    if try_move_player(0, 1, &state.world) {
-                        state.run_state = State::Running;
-                    }
-                }
+                Event::KeyDown { keycode: Some(keycode), .. } => keycode,
+                _ => continue,
+            };
 
-                _ => {}
+            let action = sdl_key_name(keycode).and_then(|key| keymap.action(&key));
+            match action {
+                Some(Action::Perform(command)) => state.apply(command),
+                Some(Action::ToggleConsole) => state.console = !state.console,
+                Some(Action::Quit) => quit = true,
+                None => {}
             }
         }
     }
@@ -3,6 +3,8 @@ use std::{
         max,
         min,
     },
+    collections::VecDeque,
+    fmt,
     ops::{
         Index,
         IndexMut,
@@ -12,7 +14,16 @@ use std::{
 use specs::Entity;
 
 use crate::{
-    log,
+    dijkstra_map::DenseGraph,
+    prefab::{
+        Legend,
+        Prefab,
+    },
+    tiles::TileType,
+    wfc::{
+        self,
+        Constraints,
+    },
 };
 
 use wavebreaker_util::{
@@ -23,18 +34,78 @@ use wavebreaker_util::{
     data_structures::Graph,
 };
 
+const TRAP_COST: f64 = 20.0;
+const WFC_ATTEMPTS: usize = 10;
+
+const ORTHOGONAL: [(i64, i64); 4] = [(0, -1), (1, 0), (0, 1), (-1, 0)];
+const DIAGONAL: [(i64, i64); 4] = [(1, -1), (1, 1), (-1, 1), (-1, -1)];
+
 #[derive(PartialEq, Copy, Clone, Debug)]
-pub enum TileType {
-    Floor,
-    Wall,
+pub enum Movement {
+    FourWay,
+    EightWay,
+    EightWayNoCornerCutting,
+}
+
+impl Movement {
+    pub fn parse(text: &str) -> Option<Movement> {
+        match text {
+            "four-way" => Some(Movement::FourWay),
+            "eight-way" => Some(Movement::EightWay),
+            "no-corner-cutting" => Some(Movement::EightWayNoCornerCutting),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Movement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Movement::FourWay => write!(f, "four-way"),
+            Movement::EightWay => write!(f, "eight-way"),
+            Movement::EightWayNoCornerCutting => write!(f, "no-corner-cutting"),
+        }
+    }
+}
+
+//which builder makes the level; wave function collapse learns either from a
+//room map or from a sample file in the prefab format
+#[derive(Clone, PartialEq, Debug)]
+pub enum Generator {
+    Rooms,
+    Wfc,
+    WfcSample(String),
+}
+
+impl Generator {
+    pub fn parse(text: &str) -> Option<Generator> {
+        let fields: Vec<&str> = text.split_whitespace().collect();
+
+        match fields.as_slice() {
+            ["rooms"] => Some(Generator::Rooms),
+            ["wfc"] => Some(Generator::Wfc),
+            ["wfc-sample", path] => Some(Generator::WfcSample(path.to_string())),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Generator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Generator::Rooms => write!(f, "rooms"),
+            Generator::Wfc => write!(f, "wfc"),
+            Generator::WfcSample(path) => write!(f, "wfc-sample {}", path),
+        }
+    }
 }
 
 #[derive(Clone, Copy, Debug)]
 pub struct Rectangle {
-    x1: usize,
-    y1: usize,
-    x2: usize,
-    y2: usize,
+    pub x1: usize,
+    pub y1: usize,
+    pub x2: usize,
+    pub y2: usize,
 }
 
 impl Rectangle {
@@ -54,12 +125,17 @@ pub struct Tile {
     pub revealed: bool,
     pub visible: bool,
     pub blocked: bool,
+    pub lock: Option<usize>,
+    pub light: (f64, f64, f64),
+    pub trapped: bool,
+    //a trap the player has found, the only kind the player's own paths avoid
+    pub known_trap: bool,
     pub entities: Vec<Entity>
 }
 
 impl FovNode for Tile {
     fn blocks_view(&self) -> bool {
-        self.tile_type == TileType::Wall
+        self.tile_type.properties().blocks_sight
     }
 }
 
@@ -68,11 +144,18 @@ pub struct Map {
     pub rooms: Vec<Rectangle>,
     pub width: usize,
     pub height: usize,
+    pub movement: Movement,
+    pub key_spawns: Vec<((usize, usize), usize)>,
+    pub spawns: Vec<((usize, usize), String)>,
+    pub prefab_rooms: Vec<usize>,
+    pub depth: usize,
+    //the tile layout after every generation step, for replaying how the map was built
+    pub history: Vec<Vec<TileType>>,
 }
 
 impl Map {
     pub fn new(width: usize, height: usize) -> Map {
-        log(&format!("Created new map with dimensions {}x{}", width - 1, height - 1));
+        info!("Created new map with dimensions {}x{}", width - 1, height - 1);
         Map {
             tiles: vec![
                 Tile { 
@@ -80,6 +163,10 @@ impl Map {
                     revealed: false, 
                     visible: false,
                     blocked: false,
+                    lock: None,
+                    light: (0.0, 0.0, 0.0),
+                    trapped: false,
+                    known_trap: false,
                     entities: Vec::new(),
                 }; 
                 width * height
@@ -87,6 +174,12 @@ impl Map {
             width: width,
             height: height,
             rooms: Vec::new(),
+            movement: Movement::EightWay,
+            key_spawns: Vec::new(),
+            spawns: Vec::new(),
+            prefab_rooms: Vec::new(),
+            depth: 1,
+            history: Vec::new(),
         }
     }
 
@@ -98,7 +191,7 @@ impl Map {
         rng: &mut SimpleRng
     ) -> Map {
         let mut map = Map::new(width, height);
-        log("Filling map with rooms"); 
+        info!("Filling map with rooms"); 
 
         for _ in 0..max_rooms {
             let x1 = rng.roll(0, width);
@@ -109,7 +202,7 @@ impl Map {
 
             let new_room = Rectangle { x1, x2, y1, y2 };
             
-            log(&format!("Generated room: {:?}", new_room));
+            debug!("Generated room: {:?}", new_room);
             let mut valid = true;
             
             if new_room.x2 >= map.width || new_room.y2 >= map.height { valid = false }
@@ -132,13 +225,110 @@ impl Map {
                 }
 
                 map.add_room(new_room);
+                map.take_snapshot();
             }
 
         }
 
+        map.add_doors(rng);
+        map.take_snapshot();
+        map.add_secret_passages(rng);
+        map.take_snapshot();
+        map.decorate_rooms(rng);
+        map.take_snapshot();
+
         map
     }
 
+    pub fn wave_function_collapse(
+        width: usize, 
+        height: usize, 
+        constraints: &Constraints, 
+        spawn_points: usize,
+        rng: &mut SimpleRng
+    ) -> Option<Map> {
+        let mut map = Map::new(width, height);
+        info!("Collapsing map from learned constraints");
+
+        let tiles = (0..WFC_ATTEMPTS).find_map(|_| wfc::collapse(constraints, width, height, rng))?;
+        for (tile, tile_type) in map.tiles.iter_mut().zip(tiles) {
+            tile.tile_type = tile_type;
+        }
+
+        //a sample without walls leaves the border undecided, but nothing may
+        //stand on the edge of the map
+        for x in 0..width {
+            for y in 0..height {
+                if x == 0 || y == 0 || x == width - 1 || y == height - 1 {
+                    map[(x, y)].tile_type = TileType::Wall;
+                }
+            }
+        }
+        map.take_snapshot();
+
+        let mut floors: Vec<(usize, usize)> = (0..width)
+            .flat_map(|x| (0..height).map(move |y| (x, y)))
+            .filter(|&tile| map[tile].tile_type == TileType::Floor)
+            .collect();
+
+        if floors.is_empty() { return None }
+
+        let spawn = floors.swap_remove(rng.roll(0, floors.len()));
+        map.connect_regions(spawn);
+
+        //there are no rooms to speak of, so spawning works off single tiles
+        //wrapped in the smallest rectangle that has them as its interior. each
+        //tile is drawn once, so nothing spawns on the player or on another spawn
+        let mut spawns = vec![spawn];
+        for _ in 0..spawn_points {
+            if floors.is_empty() { break }
+            spawns.push(floors.swap_remove(rng.roll(0, floors.len())));
+        }
+
+        for (x, y) in spawns {
+            map.rooms.push(Rectangle { x1: x - 1, y1: y - 1, x2: x + 1, y2: y + 1 });
+        }
+
+        Some(map)
+    }
+
+    //tunnels from every region the origin can't reach to the closest tile it can
+    fn connect_regions(&mut self, origin: (usize, usize)) {
+        loop {
+            let reachable = self.reachable(origin);
+            let is_reachable = |tile: (usize, usize)| reachable[(tile.0 * self.height) + tile.1];
+
+            let tiles: Vec<(usize, usize)> = (0..self.width)
+                .flat_map(|x| (0..self.height).map(move |y| (x, y)))
+                .collect();
+
+            let isolated = tiles.iter().copied().find(|&tile| {
+                Map::is_passable(self[tile].tile_type) && !is_reachable(tile)
+            });
+
+            let (x, y) = match isolated {
+                Some(tile) => tile,
+                None => return,
+            };
+
+            let distance = |&(other_x, other_y): &(usize, usize)| {
+                max(max(x, other_x) - min(x, other_x), max(y, other_y) - min(y, other_y))
+            };
+
+            let (target_x, target_y) = tiles
+                .iter()
+                .copied()
+                .filter(|&tile| is_reachable(tile))
+                .min_by_key(distance)
+                .unwrap_or(origin);
+
+            debug!("Connecting isolated region at {:?} to {:?}", (x, y), (target_x, target_y));
+            self.add_horizontal_corridor(x, target_x, y);
+            self.add_vertical_corridor(y, target_y, target_x);
+            self.take_snapshot();
+        }
+    }
+
     fn add_room(&mut self, room: Rectangle) {
         for x in room.x1 + 1 .. room.x2 {
             for y in room.y1 + 1 .. room.y2 {
@@ -169,9 +359,247 @@ impl Map {
         }
     }
 
+    fn add_doors(&mut self, rng: &mut SimpleRng) {
+        let mut leaf_rooms = Vec::new();
+
+        for (idx, room) in self.rooms.clone().iter().enumerate() {
+            let mut doorways = Vec::new();
+
+            for x in room.x1 + 1 .. room.x2 {
+                for &y in [room.y1, room.y2].iter() {
+                    if self.is_doorway((x, y), (1, 0)) { doorways.push((x, y)) }
+                }
+            }
+
+            for y in room.y1 + 1 .. room.y2 {
+                for &x in [room.x1, room.x2].iter() {
+                    if self.is_doorway((x, y), (0, 1)) { doorways.push((x, y)) }
+                }
+            }
+
+            for &doorway in doorways.iter() {
+                if rng.roll(0, 3) != 0 {
+                    self[doorway].tile_type = TileType::ClosedDoor;
+                }
+            }
+
+            if idx != 0 && doorways.len() == 1 {
+                leaf_rooms.push((idx, doorways[0]));
+            }
+        }
+
+        //a room with a single way in can be locked without cutting off the rest of
+        //the level, so its key can go in any other room
+        if !leaf_rooms.is_empty() && rng.roll(0, 2) == 1 {
+            let (locked_room, door) = leaf_rooms[rng.roll(0, leaf_rooms.len())];
+            let lock = self.key_spawns.len();
+
+            self[door].tile_type = TileType::ClosedDoor;
+            self[door].lock = Some(lock);
+
+            let mut key_room = rng.roll(0, self.rooms.len() - 1);
+            if key_room >= locked_room { key_room += 1 }
+
+            let room = self.rooms[key_room];
+            self.key_spawns.push(((room.x1 + 1, room.y1 + 1), lock));
+            debug!("Locked door at {:?}, key in room {}", door, key_room);
+        }
+    }
+
+    fn add_secret_passages(&mut self, rng: &mut SimpleRng) {
+        if self.rooms.len() < 3 { return }
+
+        for _ in 0..rng.roll(1, 3) {
+            let from = rng.roll(0, self.rooms.len() - 2);
+            let to = rng.roll(from + 2, self.rooms.len());
+
+            let (from_x, from_y) = self.rooms[from].center();
+            let (to_x, to_y) = self.rooms[to].center();
+
+            let mut passage: Vec<(usize, usize)> = (min(from_x, to_x) ..= max(from_x, to_x))
+                .map(|x| (x, from_y))
+                .collect();
+            passage.extend((min(from_y, to_y) ..= max(from_y, to_y)).map(|y| (to_x, y)));
+
+            debug!("Secret passage from room {} to room {}", from, to);
+
+            //wherever the passage breaks through a room's wall it does so
+            //through a secret door, so the loop is hidden until searched for
+            for tile in passage {
+                if self[tile].tile_type != TileType::Wall { continue }
+
+                let on_room_wall = self.rooms.iter().any(|room| {
+                    let on_x_edge = (tile.0 == room.x1 || tile.0 == room.x2)
+                        && tile.1 >= room.y1 && tile.1 <= room.y2;
+                    let on_y_edge = (tile.1 == room.y1 || tile.1 == room.y2)
+                        && tile.0 >= room.x1 && tile.0 <= room.x2;
+
+                    on_x_edge || on_y_edge
+                });
+
+                self[tile].tile_type = if on_room_wall { 
+                    TileType::SecretDoor 
+                } else { 
+                    TileType::Floor 
+                };
+            }
+        }
+    }
+
+    fn decorate_rooms(&mut self, rng: &mut SimpleRng) {
+        for room in self.rooms.clone().iter().skip(1) {
+            let center = room.center();
+
+            //keep clear of the walls and the spawn point so features can't cut
+            //a room in two or trap anything that starts in it
+            let interior: Vec<(usize, usize)> = (room.x1 + 2 .. room.x2 - 1)
+                .flat_map(|x| (room.y1 + 2 .. room.y2 - 1).map(move |y| (x, y)))
+                .filter(|&tile| tile != center)
+                .collect();
+
+            if interior.is_empty() { continue }
+
+            match rng.roll(0, 6) {
+                0 => {
+                    for &tile in interior.iter() {
+                        if rng.roll(0, 5) < 2 { self[tile].tile_type = TileType::TallGrass }
+                    }
+                },
+
+                1 => {
+                    for &tile in interior.iter() {
+                        if rng.roll(0, 5) == 0 { self[tile].tile_type = TileType::Rubble }
+                    }
+                },
+
+                2 => {
+                    let pool = interior[rng.roll(0, interior.len())];
+                    for &offset in ORTHOGONAL.iter().chain(DIAGONAL.iter()) {
+                        if let Some(tile) = self.offset(pool, offset) {
+                            if interior.contains(&tile) {
+                                self[tile].tile_type = TileType::ShallowWater;
+                            }
+                        }
+                    }
+                    self[pool].tile_type = TileType::DeepWater;
+                },
+
+                3 => {
+                    let tile = interior[rng.roll(0, interior.len())];
+                    self[tile].tile_type = TileType::Lava;
+                },
+
+                4 => {
+                    let tile = interior[rng.roll(0, interior.len())];
+                    self[tile].tile_type = TileType::Chasm;
+                },
+
+                _ => { },
+            }
+        }
+    }
+
+    //prefabs go inside rooms with at least a tile of floor left around them, so
+    //every doorway into the room still reaches every entrance of the prefab
+    pub fn stamp_prefabs(&mut self, prefabs: &[Prefab], legend: &Legend, rng: &mut SimpleRng) {
+        let allowed: Vec<&Prefab> = prefabs
+            .iter()
+            .filter(|prefab| prefab.allowed_at(self.depth))
+            .collect();
+
+        if allowed.is_empty() { return }
+
+        for (idx, room) in self.rooms.clone().iter().enumerate().skip(1) {
+            if rng.roll(0, 3) != 0 { continue }
+
+            let prefab = allowed[rng.roll(0, allowed.len())];
+            let interior_width = room.x2 - room.x1 - 1;
+            let interior_height = room.y2 - room.y1 - 1;
+            if prefab.width + 2 > interior_width || prefab.height + 2 > interior_height {
+                continue;
+            }
+
+            let origin_x = rng.roll(room.x1 + 2, room.x2 - prefab.width);
+            let origin_y = rng.roll(room.y1 + 2, room.y2 - prefab.height);
+
+            let previous = self.tiles.clone();
+            let mut spawns = Vec::new();
+            for x in 0..prefab.width {
+                for y in 0..prefab.height {
+                    let entry = legend.get(prefab.glyph(x, y)).unwrap();
+                    let tile = (origin_x + x, origin_y + y);
+
+                    self[tile].tile_type = entry.tile_type;
+                    if let Some(spawn) = &entry.spawn {
+                        spawns.push((tile, spawn.clone()));
+                    }
+                }
+            }
+
+            //a badly drawn prefab could still seal itself off, so check that
+            //everything open inside it can be reached from the first room
+            let reachable = self.reachable(self.rooms[0].center());
+            let connected = (0..prefab.width)
+                .flat_map(|x| (0..prefab.height).map(move |y| (origin_x + x, origin_y + y)))
+                .filter(|&tile| Map::is_passable(self[tile].tile_type))
+                .all(|tile| reachable[(tile.0 * self.height) + tile.1]);
+
+            if !connected {
+                debug!("Prefab {} would be unreachable in room {}", prefab.name, idx);
+                self.tiles = previous;
+                continue;
+            }
+
+            info!("Stamped prefab {} at {:?} in room {}", prefab.name, (origin_x, origin_y), idx);
+            self.spawns.extend(spawns);
+            self.prefab_rooms.push(idx);
+            self.take_snapshot();
+        }
+    }
+
+    fn take_snapshot(&mut self) {
+        let snapshot = self.tiles.iter().map(|tile| tile.tile_type).collect();
+        self.history.push(snapshot);
+    }
+
+    //flood fill over anything that can be walked or opened, indexed like tiles
+    pub fn reachable(&self, origin: (usize, usize)) -> Vec<bool> {
+        let mut reachable = vec![false; self.tiles.len()];
+        let mut frontier = VecDeque::new();
+
+        reachable[(origin.0 * self.height) + origin.1] = true;
+        frontier.push_back(origin);
+
+        while let Some(tile) = frontier.pop_front() {
+            for &offset in ORTHOGONAL.iter().chain(DIAGONAL.iter()) {
+                if let Some(next) = self.offset(tile, offset) {
+                    let idx = (next.0 * self.height) + next.1;
+                    if !reachable[idx] && Map::is_passable(self[next].tile_type) {
+                        reachable[idx] = true;
+                        frontier.push_back(next);
+                    }
+                }
+            }
+        }
+
+        reachable
+    }
+
+    fn is_passable(tile_type: TileType) -> bool {
+        tile_type == TileType::ClosedDoor || !tile_type.properties().blocks_movement
+    }
+
+    fn is_doorway(&self, tile: (usize, usize), (d_x, d_y): (i64, i64)) -> bool {
+        self[tile].tile_type == TileType::Floor
+            && self.is_wall(self.offset(tile, (d_x, d_y)))
+            && self.is_wall(self.offset(tile, (-d_x, -d_y)))
+            && !self.is_wall(self.offset(tile, (d_y, d_x)))
+            && !self.is_wall(self.offset(tile, (-d_y, -d_x)))
+    }
+
     pub fn populate_blocked(&mut self)  {
         for mut tile in self.tiles.iter_mut() {
-            if tile.tile_type == TileType::Wall {
+            if tile.tile_type.properties().blocks_movement {
                 tile.blocked = true;
             } else {
                 tile.blocked = false;
@@ -182,6 +610,63 @@ impl Map {
     pub fn clear_entities(&mut self) {
         for tile in self.tiles.iter_mut() {
             tile.entities.clear();
+            tile.trapped = false;
+            tile.known_trap = false;
+        }
+    }
+
+    pub fn propagate_noise(&self, origin: (usize, usize), volume: usize) -> Vec<usize> {
+        let mut loudness = vec![0; self.tiles.len()];
+        let mut frontier = VecDeque::new();
+
+        loudness[(origin.0 * self.height) + origin.1] = volume;
+        frontier.push_back(origin);
+
+        while let Some((x, y)) = frontier.pop_front() {
+            let current = loudness[(x * self.height) + y];
+
+            for &offset in ORTHOGONAL.iter().chain(DIAGONAL.iter()) {
+                if let Some(next) = self.offset((x, y), offset) {
+                    let dampening = self[next].tile_type.properties().sound_dampening;
+                    let next_loudness = current.saturating_sub(dampening);
+                    let idx = (next.0 * self.height) + next.1;
+                    if next_loudness > loudness[idx] {
+                        loudness[idx] = next_loudness;
+                        frontier.push_back(next);
+                    }
+                }
+            }
+        }
+
+        loudness
+    }
+
+    pub fn offset(&self, (x, y): (usize, usize), (d_x, d_y): (i64, i64)) -> Option<(usize, usize)> {
+        let next_x = x as i64 + d_x;
+        let next_y = y as i64 + d_y;
+
+        if next_x < 0 || next_y < 0 { return None }
+
+        let next = (next_x as usize, next_y as usize);
+        if self.contains(&next) { Some(next) } else { None }
+    }
+
+    fn is_wall(&self, tile: Option<(usize, usize)>) -> bool {
+        match tile {
+            Some(tile) => self[tile].tile_type == TileType::Wall,
+            None => true,
+        }
+    }
+
+    //anything a diagonal step can't squeeze past, judged by how the tile looks
+    //so that a secret door makes the same corner as the wall it passes for
+    fn is_solid(&self, tile: Option<(usize, usize)>) -> bool {
+        match tile {
+            Some(tile) => {
+                let properties = self[tile].tile_type.properties();
+                properties.blocks_movement && properties.blocks_sight
+            },
+            None => true,
         }
     }
 
@@ -190,12 +675,23 @@ impl Map {
 impl Graph<(usize, usize), Tile> for Map {
     fn neighbors(&self, tile: &(usize, usize)) -> Vec<(usize, usize)> {
         let mut neighbors = Vec::new();
-        for x in -1..=1 {
-            for y in -1..=1 {
-                let potential_neighbor = (
-                    (tile.0 as i64 + x) as usize, 
-                    (tile.1 as i64 + y) as usize
-                );
+
+        for &offset in ORTHOGONAL.iter() {
+            if let Some(potential_neighbor) = self.offset(*tile, offset) {
+                if !self[potential_neighbor].blocked {
+                    neighbors.push(potential_neighbor);
+                }
+            }
+        }
+
+        if self.movement == Movement::FourWay {
+            return neighbors;
+        }
+
+        for &(d_x, d_y) in DIAGONAL.iter() {
+            if let Some(potential_neighbor) = self.offset(*tile, (d_x, d_y)) {
+                if !self.allows_diagonal(*tile, (d_x, d_y)) { continue }
+
                 if !self[potential_neighbor].blocked {
                     neighbors.push(potential_neighbor);
                 }
@@ -206,6 +702,45 @@ impl Graph<(usize, usize), Tile> for Map {
     }
 
     fn cost(&self, start: &(usize, usize), end: &(usize, usize)) -> f64 {
+        self.cost_avoiding(start, end, |tile| tile.trapped)
+    }
+
+    fn contains(&self, point: &(usize, usize)) -> bool {
+        point.0 < self.width && point.1 < self.height
+    }
+}
+
+impl DenseGraph<(usize, usize), Tile> for Map {
+    fn node_count(&self) -> usize {
+        self.tiles.len()
+    }
+
+    fn node_index(&self, (x, y): &(usize, usize)) -> usize {
+        (x * self.height) + y
+    }
+
+    fn node_at(&self, index: usize) -> (usize, usize) {
+        (index / self.height, index % self.height)
+    }
+}
+
+impl Map {
+    //the same rule for the player as for everything that paths over the graph
+    pub fn allows_diagonal(&self, tile: (usize, usize), (d_x, d_y): (i64, i64)) -> bool {
+        if self.movement == Movement::FourWay { return false }
+
+        self.movement != Movement::EightWayNoCornerCutting
+            || !(self.is_solid(self.offset(tile, (d_x, 0))) || self.is_solid(self.offset(tile, (0, d_y))))
+    }
+
+    //the graph cost, with `avoid` deciding which tiles count as trapped for
+    //whoever is doing the pathing
+    pub fn cost_avoiding(
+        &self,
+        start: &(usize, usize),
+        end: &(usize, usize),
+        avoid: impl Fn(&Tile) -> bool
+    ) -> f64 {
         let x_distance;
         let y_distance;
         
@@ -221,11 +756,17 @@ impl Graph<(usize, usize), Tile> for Map {
             y_distance = end.1 - start.1
         }
         
-        (x_distance as f64 + y_distance as f64).sqrt()
-    }
-
-    fn contains(&self, point: &(usize, usize)) -> bool {
-        point.0 < self.width && point.1 < self.height
+        let distance = ((x_distance * x_distance + y_distance * y_distance) as f64).sqrt();
+
+        //single steps pay for the terrain they enter, longer spans are estimates
+        //and stay plain distances so they never overestimate. monsters know where
+        //their level's traps are, so trapped tiles are priced to be walked around
+        if x_distance <= 1 && y_distance <= 1 {
+            let trap_cost = if avoid(&self[*end]) { TRAP_COST } else { 0.0 };
+            distance * self[*end].tile_type.properties().movement_cost + trap_cost
+        } else {
+            distance
+        }
     }
 }
 
@@ -1,5 +1,5 @@
 use specs::prelude::*;
-use super::{Map, Position, BlocksTile};
+use super::{Map, Position, BlocksTile, Hidden, Trap};
 
 pub struct MapProcessing { }
 
@@ -7,10 +7,12 @@ impl<'a> System<'a> for MapProcessing {
     type SystemData = ( WriteExpect<'a, Map>,
                         ReadStorage<'a, Position>,
                         ReadStorage<'a, BlocksTile>,
+                        ReadStorage<'a, Trap>,
+                        ReadStorage<'a, Hidden>,
                         Entities<'a>);
 
     fn run(&mut self, data : Self::SystemData) {
-        let (mut map, position, blockers, entities) = data;
+        let (mut map, position, blockers, traps, hidden, entities) = data;
 
         map.populate_blocked();
         map.clear_entities();
@@ -19,6 +21,12 @@ impl<'a> System<'a> for MapProcessing {
                 map[(position.x, position.y)].blocked = true;
             }
 
+            if traps.contains(entity) {
+                map[(position.x, position.y)].trapped = true;
+                //one found trap is enough, whatever else shares the tile
+                map[(position.x, position.y)].known_trap |= !hidden.contains(entity);
+            }
+
             map[(position.x, position.y)].entities.push(entity);  
         }
     }
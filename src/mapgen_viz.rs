@@ -0,0 +1,135 @@
+use std::time::{
+    Duration,
+    Instant,
+};
+
+use crate::{
+    Color,
+    Event,
+    FontCache,
+    Keycode,
+    map::Map,
+    prefab::Legend,
+    renderer::{
+        Renderer,
+        SdlRenderer,
+    },
+    tiles::TileType,
+    View,
+};
+
+const FRAME_DELAY: Duration = Duration::from_millis(250);
+
+//frames are written with the prefab legend's glyphs, so a frame can be read
+//the same way as a prefab layout
+pub fn dump(map: &Map, legend: &Legend) -> String {
+    let mut frames = String::new();
+
+    for (frame, snapshot) in map.history.iter().enumerate() {
+        frames.push_str(&format!("frame {}/{}\n", frame + 1, map.history.len()));
+
+        for y in 0..map.height {
+            for x in 0..map.width {
+                let tile_type = snapshot[(x * map.height) + y];
+                frames.push(legend.glyph_for(tile_type).unwrap_or('?'));
+            }
+            frames.push('\n');
+        }
+    }
+
+    frames
+}
+
+//space pauses, the arrow keys step while paused, escape quits
+pub fn run(
+    view: &mut View,
+    font: &mut FontCache,
+    cell_size: (u32, u32),
+    map: &Map
+) -> Result<(), String> {
+    if map.history.is_empty() { return Ok(()) }
+
+    let mut event_pump = view.event_pump()?;
+    let mut renderer = SdlRenderer { view, font, cell_size };
+    let mut frame = 0;
+    let mut paused = false;
+    let mut last_step = Instant::now();
+
+    loop {
+        for event in event_pump.poll_iter() {
+            match event {
+                Event::Quit {..} |
+                Event::KeyDown { keycode: Some(Keycode::Escape), .. } => {
+                    return Ok(());
+                },
+
+                Event::KeyDown { keycode: Some(Keycode::Space), .. } => {
+                    paused = !paused;
+                },
+
+                Event::KeyDown { keycode: Some(Keycode::Right), .. } => {
+                    paused = true;
+                    frame = (frame + 1).min(map.history.len() - 1);
+                },
+
+                Event::KeyDown { keycode: Some(Keycode::Left), .. } => {
+                    paused = true;
+                    frame = frame.saturating_sub(1);
+                },
+
+                _ => {}
+            }
+        }
+
+        if !paused && frame + 1 < map.history.len() && last_step.elapsed() >= FRAME_DELAY {
+            frame += 1;
+            last_step = Instant::now();
+        }
+
+        renderer.clear();
+        draw_snapshot(&mut renderer, map, &map.history[frame]);
+
+        let status = format!(
+            "frame {}/{}{}",
+            frame + 1,
+            map.history.len(),
+            if paused { " (paused)" } else { "" }
+        );
+        renderer.draw_text(1, map.height - 2, &status, Color::RGB(255, 255, 255));
+        renderer.present();
+    }
+}
+
+fn draw_snapshot(renderer: &mut dyn Renderer, map: &Map, snapshot: &[TileType]) {
+    for (idx, tile_type) in snapshot.iter().enumerate() {
+        let x = idx / map.height;
+        let y = idx % map.height;
+
+        let properties = tile_type.properties();
+        let (foreground, background) = (properties.foreground, properties.background);
+
+        renderer.draw_glyph(x,
+            y,
+            properties.glyph,
+            Color::RGB(foreground.0, foreground.1, foreground.2),
+            Color::RGB(background.0, background.1, background.2)
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::snapshot::{
+        assert_snapshot,
+        seeded_level,
+    };
+
+    use super::dump;
+
+    #[test]
+    fn room_generation_is_unchanged() {
+        let (map, legend, _) = seeded_level();
+
+        assert_snapshot("mapgen_rooms_1234", &dump(&map, &legend));
+    }
+}
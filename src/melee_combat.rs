@@ -1,5 +1,6 @@
 use specs::prelude::*;
-use super::{CombatStats, MeleeAttack, IncomingDamage};
+use super::{CombatStats, MeleeAttack, IncomingDamage, Position};
+use crate::hearing::{COMBAT_NOISE, Noise};
 
 pub struct MeleeCombat {}
 
@@ -7,7 +8,9 @@ impl<'a> System<'a> for MeleeCombat {
     type SystemData = ( Entities<'a>,
                         WriteStorage<'a, MeleeAttack>,
                         ReadStorage<'a, CombatStats>,
-                        WriteStorage<'a, IncomingDamage>
+                        WriteStorage<'a, IncomingDamage>,
+                        ReadStorage<'a, Position>,
+                        WriteExpect<'a, Vec<Noise>>
                       );
 
     fn run(&mut self, data : Self::SystemData) {
@@ -15,13 +18,17 @@ impl<'a> System<'a> for MeleeCombat {
             entities, 
             mut melee_attacks,
             combat_stats, 
-            mut inflict_damage
+            mut inflict_damage,
+            positions,
+            mut noises
         ) = data;
 
-        for (_entity, attack, stats) in 
-            (&entities, &melee_attacks, &combat_stats).join() 
+        for (_entity, attack, stats, position) in 
+            (&entities, &melee_attacks, &combat_stats, &positions).join() 
         {
             if stats.hp > 0 {
+                noises.push(Noise { x: position.x, y: position.y, volume: COMBAT_NOISE });
+
                 let target_stats = combat_stats.get(attack.target).unwrap();
                 if target_stats.hp > 0 {
                     let damage = stats.power.saturating_sub(target_stats.defense);
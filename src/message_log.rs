@@ -0,0 +1,23 @@
+use crate::Queue;
+
+//the last few messages, along with how many there have been in total so
+//anything waiting on the player can tell when a new one arrives
+pub struct MessageLog {
+    messages: Queue<String>,
+    count: usize,
+}
+
+impl MessageLog {
+    pub fn new(capacity: usize) -> MessageLog {
+        MessageLog { messages: Queue::new(capacity), count: 0 }
+    }
+
+    pub fn push(&mut self, message: String) {
+        self.messages.push(message);
+        self.count += 1;
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+}
@@ -1,41 +1,85 @@
 use specs::prelude::*;
 use crate::{
-    log,
     components::*,
+    dijkstra_map::{
+        DijkstraMap,
+        FLEE_COEFFICIENT,
+    },
+    factions::{
+        FactionTable,
+        Reaction,
+        reaction_between,
+    },
     map::Tile,
     Map, 
+    MessageLog,
 };
 
 use wavebreaker_util::{
     algorithms::{
         fov::compute_fov,
         pathfinding::find_path,
+        simple_rng::SimpleRng,
     },
-    data_structures::Queue,
+    data_structures::Graph,
 };
 
+pub const SEARCH_TURNS: usize = 5;
+
 pub struct MonsterAi;
 
 impl<'a> System<'a> for MonsterAi {
-    type SystemData = (WriteExpect<'a, Queue<String>>, 
+    type SystemData = (Entities<'a>,
+                       WriteExpect<'a, MessageLog>, 
+                       WriteExpect<'a, SimpleRng>,
                        ReadExpect<'a, Position>,
                        ReadExpect<'a, Map>,
+                       ReadExpect<'a, FactionTable>,
                        WriteStorage<'a, Viewshed>, 
                        WriteStorage<'a, Position>,
+                       WriteStorage<'a, Memory>,
+                       WriteStorage<'a, MeleeAttack>,
+                       WriteStorage<'a, Morale>,
+                       WriteStorage<'a, Path>,
+                       ReadStorage<'a, CombatStats>,
+                       ReadStorage<'a, Companion>,
+                       ReadStorage<'a, Faction>,
                        ReadStorage<'a, Monster>,
+                       ReadStorage<'a, Player>,
                        ReadStorage<'a, Name>);
 
     fn run(&mut self, data: Self::SystemData) {
-        let (mut messages, 
-             player_position, 
+        let (entities,
+             mut messages, 
+             mut rng,
+             player_position,
              map, 
+             faction_table,
              mut viewsheds, 
              mut positions, 
+             mut memories,
+             mut melee_attacks,
+             mut morales,
+             mut paths,
+             combat_stats,
+             companions,
+             factions,
              monsters, 
+             players,
              names) = data;
+
+        let candidates: Vec<(Entity, Position)> = (&entities, &positions, &factions)
+            .join()
+            .map(|(entity, position, _)| (entity, *position))
+            .collect();
+
+        //computed once per turn and shared by everything chasing or avoiding the player
+        let player_tile = (player_position.x, player_position.y);
+        let player_map = DijkstraMap::new(&*map, &[(player_tile, 0.0)]);
+        let mut safety_map: Option<DijkstraMap<(usize, usize)>> = None;
         
-        for (mut viewshed, mut position, _, name) 
-            in (&mut viewsheds, &mut positions, &monsters, &names).join() 
+        for (entity, mut viewshed, position, memory, _, name) 
+            in (&entities, &mut viewsheds, &mut positions, &mut memories, &monsters, &names).join() 
         {
             if viewshed.dirty {
                 viewshed.visible_tiles = compute_fov(
@@ -47,16 +91,109 @@ impl<'a> System<'a> for MonsterAi {
                 viewshed.dirty = false;
             }
 
-            if viewshed.visible_tiles.contains(&(player_position.x, player_position.y)) {
-                messages.push(format!("{} hurls insults at you!", name.name));
-                let mut path = find_path::<Tile>(
-                    (position.x, position.y), 
-                    (player_position.x, player_position.y), 
-                    &*map
-                );
+            if let Some(companion) = companions.get(entity) {
+                if position.distance(&player_position) > companion.leash {
+                    if let Some(next_tile) = step_downhill(&map, &player_map, position, player_tile) {
+                        *position = Position { x: next_tile.0, y: next_tile.1 };
+                        viewshed.dirty = true;
+                    }
+
+                    continue;
+                }
+            }
+
+            if let Some(morale) = morales.get_mut(entity) {
+                let hp = combat_stats.get(entity).map_or(0, |stats| stats.hp);
+
+                if hp < morale.threshold {
+                    let safety = safety_map.get_or_insert_with(|| {
+                        player_map.rescan(&*map, FLEE_COEFFICIENT)
+                    });
+
+                    let here = (position.x, position.y);
+                    let current = safety.value(&*map, &here)
+                        .min(player_map.value(&*map, &here) * FLEE_COEFFICIENT);
+
+                    match safety.downhill(&*map, &here, current) {
+                        Some(next_tile) => {
+                            if !morale.fleeing {
+                                messages.push(format!("{} flees in terror!", name.name));
+                                morale.fleeing = true;
+                            }
+
+                            *position = Position { x: next_tile.0, y: next_tile.1 };
+                            viewshed.dirty = true;
+                            continue;
+                        },
+
+                        None => {
+                            if morale.fleeing {
+                                messages.push(format!("{} is cornered and turns to fight!", name.name));
+                                morale.fleeing = false;
+                            }
+                        },
+                    }
+                } else if morale.fleeing {
+                    messages.push(format!("{} regains its courage!", name.name));
+                    morale.fleeing = false;
+                }
+            }
+
+            let target = candidates
+                .iter()
+                .filter(|(other, other_position)| {
+                    *other != entity
+                        && viewshed.visible_tiles.contains(&(other_position.x, other_position.y))
+                        && reaction_between(&factions, &faction_table, entity, *other) == Reaction::Hostile
+                })
+                .min_by_key(|(_, other_position)| position.distance(other_position));
+
+            if let Some(&(target, target_position)) = target {
+                if players.contains(target) {
+                    messages.push(format!("{} hurls insults at you!", name.name));
+                }
+                memory.last_seen = Some(target_position);
+                memory.search_turns = SEARCH_TURNS;
+
+                if position.distance(&target_position) <= 1 {
+                    melee_attacks.insert(entity, MeleeAttack { target })
+                        .expect("Could not add attack");
+                } else {
+                    let next_tile = if players.contains(target) {
+                        step_downhill(&map, &player_map, position, player_tile)
+                    } else {
+                        follow_path(&map, &mut paths, entity, position, &target_position)
+                    };
+
+                    if let Some(next_tile) = next_tile {
+                        *position = Position { x: next_tile.0, y: next_tile.1 };
+                        viewshed.dirty = true;
+                    }
+                }
+            } else if let Some(last_seen) = memory.last_seen {
+                let next_tile = if position.distance(&last_seen) == 0 {
+                    None
+                } else {
+                    follow_path(&map, &mut paths, entity, position, &last_seen)
+                };
 
-                if path.len() > 1 {
-                    let next_tile = path.pop().unwrap();
+                match next_tile {
+                    Some(next_tile) => {
+                        *position = Position { x: next_tile.0, y: next_tile.1 };
+                        viewshed.dirty = true;
+                    },
+
+                    _ => {
+                        debug!("{} lost track of its target", name.name);
+                        memory.last_seen = None;
+                    },
+                }
+            } else if memory.search_turns > 0 {
+                memory.search_turns -= 1;
+
+                let neighbors = map.neighbors(&(position.x, position.y));
+                if !neighbors.is_empty() {
+                    let next_tile = neighbors[rng.roll(0, neighbors.len())];
                     *position = Position { x: next_tile.0, y: next_tile.1 };
                     viewshed.dirty = true;
                 }
@@ -65,3 +202,80 @@ impl<'a> System<'a> for MonsterAi {
     }
 }
 
+fn step_downhill(
+    map: &Map, 
+    dijkstra_map: &DijkstraMap<(usize, usize)>, 
+    position: &Position, 
+    goal: (usize, usize)
+) -> Option<(usize, usize)> {
+    let here = (position.x, position.y);
+    let current = dijkstra_map.value(map, &here);
+
+    dijkstra_map.downhill(map, &here, current)
+        .filter(|&next_tile| next_tile != goal)
+}
+
+fn follow_path(
+    map: &Map, 
+    paths: &mut WriteStorage<Path>, 
+    entity: Entity, 
+    position: &Position, 
+    goal: &Position
+) -> Option<(usize, usize)> {
+    let here = (position.x, position.y);
+    let goal_tile = (goal.x, goal.y);
+
+    //only plan again once the goal has moved or we've been pushed off the route
+    let stale = match paths.get(entity) {
+        Some(path) => path.goal != goal_tile || match path.steps.last() {
+            Some(&(x, y)) => Position { x, y }.distance(position) != 1,
+            None => true,
+        },
+        None => true,
+    };
+
+    if stale {
+        let steps = find_path::<Tile>(here, goal_tile, map);
+        paths.insert(entity, Path { goal: goal_tile, steps }).expect("Could not cache path");
+    }
+
+    let path = paths.get_mut(entity).expect("No cached path");
+    if let Some(&next_tile) = path.steps.last() {
+        if map[next_tile].blocked && next_tile != goal_tile {
+            repair_path(map, path, here);
+        }
+    }
+
+    match path.steps.pop() {
+        Some(next_tile) if !map[next_tile].blocked => Some(next_tile),
+
+        _ => {
+            paths.remove(entity);
+
+            //blocking goals can't be pathed onto, so close in greedily
+            map.neighbors(&here)
+                .into_iter()
+                .filter(|&(x, y)| Position { x, y }.distance(goal) < position.distance(goal))
+                .min_by_key(|&(x, y)| Position { x, y }.distance(goal))
+        },
+    }
+}
+
+//rather than planning the whole route again, detour from here to the first
+//waypoint past the blockage and keep the rest of the cached path
+fn repair_path(map: &Map, path: &mut Path, here: (usize, usize)) {
+    match path.steps.iter().rposition(|&step| !map[step].blocked) {
+        Some(rejoin) => {
+            let detour = find_path::<Tile>(here, path.steps[rejoin], map);
+            path.steps.truncate(rejoin);
+
+            if detour.is_empty() {
+                path.steps.clear();
+            } else {
+                path.steps.extend(detour);
+            }
+        },
+
+        None => path.steps.clear(),
+    }
+}
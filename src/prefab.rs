@@ -0,0 +1,224 @@
+use std::{
+    collections::HashMap,
+    fs,
+};
+
+use crate::tiles::TileType;
+
+pub struct LegendEntry {
+    pub tile_type: TileType,
+    pub spawn: Option<String>,
+}
+
+pub struct Legend {
+    entries: HashMap<char, LegendEntry>,
+}
+
+impl Legend {
+    pub fn load(path: &str) -> Result<Legend, String> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| format!("Could not read prefab legend {}: {}", path, e))?;
+
+        let mut entries = HashMap::new();
+        for (line_number, line) in contents.lines().enumerate() {
+            if line.trim().is_empty() || line.starts_with("//") { continue }
+
+            //the glyph is the first character of the line, which may itself be
+            //whitespace-like or a `#`, so it's taken before splitting
+            let mut chars = line.chars();
+            let glyph = chars.next().unwrap();
+            let fields: Vec<&str> = chars.as_str().split_whitespace().collect();
+            if fields.is_empty() || fields.len() > 2 {
+                return Err(format!("{}:{}: expected `glyph tile [spawn]`", path, line_number + 1));
+            }
+
+            let tile_type = match fields[0] {
+                "chasm" => TileType::Chasm,
+                "closed_door" => TileType::ClosedDoor,
+                "deep_water" => TileType::DeepWater,
+                "floor" => TileType::Floor,
+                "lava" => TileType::Lava,
+                "open_door" => TileType::OpenDoor,
+                "rubble" => TileType::Rubble,
+                "secret_door" => TileType::SecretDoor,
+                "shallow_water" => TileType::ShallowWater,
+                "tall_grass" => TileType::TallGrass,
+                "wall" => TileType::Wall,
+                other => return Err(format!("{}:{}: unknown tile {}", path, line_number + 1, other)),
+            };
+
+            let spawn = fields.get(1).map(|spawn| spawn.to_string());
+            entries.insert(glyph, LegendEntry { tile_type, spawn });
+        }
+
+        Ok(Legend { entries })
+    }
+
+    pub fn get(&self, glyph: char) -> Option<&LegendEntry> {
+        self.entries.get(&glyph)
+    }
+
+    //the glyph that stamps a tile without spawning anything on it, lowest
+    //first if there are several so the choice doesn't depend on hash order
+    pub fn glyph_for(&self, tile_type: TileType) -> Option<char> {
+        self.entries
+            .iter()
+            .filter(|(_, entry)| entry.tile_type == tile_type && entry.spawn.is_none())
+            .map(|(&glyph, _)| glyph)
+            .min()
+    }
+}
+
+pub struct Prefab {
+    pub name: String,
+    pub min_depth: usize,
+    pub max_depth: usize,
+    pub width: usize,
+    pub height: usize,
+    rows: Vec<Vec<char>>,
+}
+
+impl Prefab {
+    //a header of `key: value` lines, a blank line, then the layout
+    pub fn load(path: &str, legend: &Legend) -> Result<Prefab, String> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| format!("Could not read prefab {}: {}", path, e))?;
+
+        let mut name = path.to_string();
+        let mut depth = (1, usize::MAX);
+        let mut lines = contents.lines().enumerate();
+
+        for (line_number, line) in lines.by_ref() {
+            if line.trim().is_empty() { break }
+
+            let (key, value) = match line.find(':') {
+                Some(split) => (line[..split].trim(), line[split + 1..].trim()),
+                None => return Err(format!("{}:{}: expected `key: value`", path, line_number + 1)),
+            };
+
+            match key {
+                "name" => name = value.to_string(),
+                "depth" => depth = parse_depth(value)
+                    .ok_or(format!("{}:{}: expected `depth: min-max`", path, line_number + 1))?,
+                other => return Err(format!("{}:{}: unknown key {}", path, line_number + 1, other)),
+            }
+        }
+
+        let mut rows = Vec::new();
+        for (line_number, line) in lines {
+            if line.is_empty() { continue }
+
+            let row: Vec<char> = line.chars().collect();
+            if let Some(glyph) = row.iter().find(|&&glyph| legend.get(glyph).is_none()) {
+                return Err(format!("{}:{}: {:?} is not in the legend", path, line_number + 1, glyph));
+            }
+
+            rows.push(row);
+        }
+
+        let height = rows.len();
+        let width = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+        if width == 0 || rows.iter().any(|row| row.len() != width) {
+            return Err(format!("{}: layout must be a non-empty rectangle", path));
+        }
+
+        Ok(Prefab {
+            name,
+            min_depth: depth.0,
+            max_depth: depth.1,
+            width,
+            height,
+            rows,
+        })
+    }
+
+    pub fn load_all(directory: &str, legend: &Legend) -> Result<Vec<Prefab>, String> {
+        let entries = fs::read_dir(directory)
+            .map_err(|e| format!("Could not read prefab directory {}: {}", directory, e))?;
+
+        let mut paths = Vec::new();
+        for entry in entries {
+            let path = entry.map_err(|e| format!("{}: {}", directory, e))?.path();
+            if path.extension().map_or(false, |extension| extension == "prefab") {
+                paths.push(path.to_string_lossy().to_string());
+            }
+        }
+
+        //directory order isn't stable, and the same seed should pick the same prefabs
+        paths.sort();
+        paths.iter().map(|path| Prefab::load(path, legend)).collect()
+    }
+
+    pub fn allowed_at(&self, depth: usize) -> bool {
+        depth >= self.min_depth && depth <= self.max_depth
+    }
+
+    pub fn glyph(&self, x: usize, y: usize) -> char {
+        self.rows[y][x]
+    }
+}
+
+fn parse_depth(value: &str) -> Option<(usize, usize)> {
+    let mut bounds = value.split('-').map(|bound| bound.trim());
+    let min = bounds.next()?.parse().ok()?;
+    let max = match bounds.next() {
+        Some("") => usize::MAX,
+        Some(bound) => bound.parse().ok()?,
+        None => min,
+    };
+
+    if bounds.next().is_some() || max < min { return None }
+    Some((min, max))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        parse_depth,
+        Prefab,
+    };
+
+    fn prefab(depth: &str) -> Prefab {
+        let (min_depth, max_depth) = parse_depth(depth).unwrap();
+        Prefab {
+            name: depth.to_string(),
+            min_depth,
+            max_depth,
+            width: 1,
+            height: 1,
+            rows: vec![vec!['.']],
+        }
+    }
+
+    #[test]
+    fn depth_ranges_parse() {
+        assert_eq!(parse_depth("2-4"), Some((2, 4)));
+        assert_eq!(parse_depth("3"), Some((3, 3)));
+        assert_eq!(parse_depth("2-"), Some((2, usize::MAX)));
+        assert_eq!(parse_depth(" 1 - 5 "), Some((1, 5)));
+
+        assert_eq!(parse_depth("4-2"), None);
+        assert_eq!(parse_depth("1-2-3"), None);
+        assert_eq!(parse_depth("-2"), None);
+        assert_eq!(parse_depth("deep"), None);
+    }
+
+    #[test]
+    fn prefabs_are_allowed_within_both_bounds() {
+        let bounded = prefab("2-4");
+        assert!(!bounded.allowed_at(1));
+        assert!(bounded.allowed_at(2));
+        assert!(bounded.allowed_at(4));
+        assert!(!bounded.allowed_at(5));
+
+        let single = prefab("3");
+        assert!(!single.allowed_at(2));
+        assert!(single.allowed_at(3));
+        assert!(!single.allowed_at(4));
+
+        let open = prefab("2-");
+        assert!(!open.allowed_at(1));
+        assert!(open.allowed_at(2));
+        assert!(open.allowed_at(usize::MAX));
+    }
+}
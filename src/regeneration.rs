@@ -0,0 +1,27 @@
+use specs::prelude::*;
+use super::{CombatStats, Regeneration};
+
+pub struct Regenerate {}
+
+impl<'a> System<'a> for Regenerate {
+    type SystemData = ( WriteStorage<'a, CombatStats>,
+                        WriteStorage<'a, Regeneration> );
+
+    fn run(&mut self, data : Self::SystemData) {
+        let (mut stats, mut regeneration) = data;
+
+        for (stats, regeneration) in (&mut stats, &mut regeneration).join() {
+            //the dead stay dead, the healthy have nothing to recover
+            if stats.hp == 0 || stats.hp >= stats.max_hp {
+                regeneration.progress = 0;
+                continue;
+            }
+
+            regeneration.progress += 1;
+            if regeneration.progress >= regeneration.turns_per_hp {
+                regeneration.progress = 0;
+                stats.hp += 1;
+            }
+        }
+    }
+}
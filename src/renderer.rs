@@ -0,0 +1,176 @@
+use std::fmt;
+
+use crate::{
+    Color,
+    FontCache,
+    Rect,
+    View,
+};
+
+const TEXT_SIZE: u16 = 16;
+
+//everything is drawn in map cells, each backend decides what a cell is
+pub trait Renderer {
+    fn clear(&mut self);
+    fn draw_glyph(&mut self, x: usize, y: usize, glyph: char, foreground: Color, background: Color);
+    fn draw_text(&mut self, x: usize, y: usize, text: &str, foreground: Color);
+    fn present(&mut self);
+}
+
+pub struct SdlRenderer<'a, 'b> {
+    pub view: &'b mut View,
+    pub font: &'b mut FontCache<'a>,
+    pub cell_size: (u32, u32),
+}
+
+impl<'a, 'b> Renderer for SdlRenderer<'a, 'b> {
+    fn clear(&mut self) {
+        self.view.clear();
+    }
+
+    fn draw_glyph(&mut self, x: usize, y: usize, glyph: char, foreground: Color, background: Color) {
+        self.view.draw_glyph(
+            self.font,
+            glyph,
+            foreground,
+            background,
+            Rect::new(
+                (x as u32 * self.cell_size.0) as i32,
+                (y as u32 * self.cell_size.1) as i32,
+                self.cell_size.0,
+                self.cell_size.1
+            )
+        ).expect("Could not render glyph");
+    }
+
+    fn draw_text(&mut self, x: usize, y: usize, text: &str, foreground: Color) {
+        self.view.draw_text(
+            self.font,
+            text,
+            foreground,
+            Color::RGBA(0, 0, 0, 0),
+            ((x as u32 * self.cell_size.0) as i32, (y as u32 * self.cell_size.1) as i32),
+            TEXT_SIZE
+        ).expect("Could not render text");
+    }
+
+    fn present(&mut self) {
+        self.view.present();
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Cell {
+    pub glyph: char,
+    pub foreground: Color,
+    pub background: Color,
+}
+
+const BLANK: Cell = Cell {
+    glyph: ' ',
+    foreground: Color { r: 0, g: 0, b: 0, a: 255 },
+    background: Color { r: 0, g: 0, b: 0, a: 255 },
+};
+
+//renders into memory so whole frames can be compared without a display
+pub struct TextGrid {
+    pub width: usize,
+    pub height: usize,
+    cells: Vec<Cell>,
+}
+
+impl TextGrid {
+    pub fn new(width: usize, height: usize) -> TextGrid {
+        TextGrid {
+            width,
+            height,
+            cells: vec![BLANK; width * height],
+        }
+    }
+
+    pub fn cell(&self, x: usize, y: usize) -> Cell {
+        self.cells[(y * self.width) + x]
+    }
+}
+
+impl Renderer for TextGrid {
+    fn clear(&mut self) {
+        for cell in self.cells.iter_mut() {
+            *cell = BLANK;
+        }
+    }
+
+    fn draw_glyph(&mut self, x: usize, y: usize, glyph: char, foreground: Color, background: Color) {
+        if x >= self.width || y >= self.height { return }
+
+        self.cells[(y * self.width) + x] = Cell { glyph, foreground, background };
+    }
+
+    //text keeps whatever background is already under it, like it does on screen
+    fn draw_text(&mut self, x: usize, y: usize, text: &str, foreground: Color) {
+        if y >= self.height { return }
+
+        for (offset, glyph) in text.chars().enumerate() {
+            if x + offset >= self.width { break }
+
+            let cell = &mut self.cells[(y * self.width) + x + offset];
+            cell.glyph = glyph;
+            cell.foreground = foreground;
+        }
+    }
+
+    fn present(&mut self) { }
+}
+
+//the glyphs, one line per row, then every cell's colors as `fg/bg` in hex
+//with the rows in the same order
+impl fmt::Display for TextGrid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for y in 0..self.height {
+            let line: String = (0..self.width).map(|x| self.cell(x, y).glyph).collect();
+            writeln!(f, "{}", line)?;
+        }
+
+        writeln!(f)?;
+
+        for y in 0..self.height {
+            let colors: Vec<String> = (0..self.width)
+                .map(|x| {
+                    let cell = self.cell(x, y);
+                    format!("{}/{}", hex(cell.foreground), hex(cell.background))
+                })
+                .collect();
+            writeln!(f, "{}", colors.join(" "))?;
+        }
+
+        Ok(())
+    }
+}
+
+fn hex(color: Color) -> String {
+    format!("{:02x}{:02x}{:02x}", color.r, color.g, color.b)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        new_game,
+        snapshot::{
+            assert_snapshot,
+            seeded_level,
+        },
+    };
+
+    use super::TextGrid;
+
+    #[test]
+    fn first_frame_is_unchanged() {
+        let (map, _, rng) = seeded_level();
+        let mut grid = TextGrid::new(map.width, map.height);
+        let mut state = new_game(map, rng).unwrap();
+
+        state.tick(&mut grid);
+
+        assert_snapshot("frame_1234", &grid.to_string());
+    }
+}
@@ -0,0 +1,198 @@
+use std::{
+    fs::{
+        self,
+        File,
+    },
+    io::Write,
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+use specs::prelude::*;
+
+use crate::{
+    app::{
+        App,
+        State,
+    },
+    commands::Command,
+    components::{
+        CombatStats,
+        Position,
+    },
+    Event,
+    FontCache,
+    Keycode,
+    map::Map,
+    renderer::SdlRenderer,
+    View,
+};
+
+const REPLAY_DELAY: Duration = Duration::from_millis(100);
+
+//a file of `set` lines with the settings the level was generated from,
+//then one line per command ending in the checksum of the world right after it
+pub struct Recorder {
+    file: File,
+}
+
+impl Recorder {
+    pub fn create(path: &str, settings: &[(&str, String)]) -> Result<Recorder, String> {
+        let mut file = File::create(path)
+            .map_err(|e| format!("Could not create replay file {}: {}", path, e))?;
+
+        for (key, value) in settings {
+            writeln!(file, "set {} {}", key, value)
+                .map_err(|e| format!("Could not write replay file {}: {}", path, e))?;
+        }
+
+        Ok(Recorder { file })
+    }
+
+    pub fn record(&mut self, command: Command, checksum: u64) {
+        writeln!(self.file, "{} {:016x}", command, checksum).expect("Could not write replay file");
+    }
+}
+
+pub struct Step {
+    pub command: Command,
+    pub checksum: u64,
+}
+
+pub struct Replay {
+    pub settings: Vec<(String, String)>,
+    pub steps: Vec<Step>,
+}
+
+impl Replay {
+    pub fn load(path: &str) -> Result<Replay, String> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| format!("Could not read replay {}: {}", path, e))?;
+
+        let mut settings = Vec::new();
+        let mut steps = Vec::new();
+
+        for (line_number, line) in contents.lines().enumerate() {
+            let error = || format!("{}:{}: could not parse {:?}", path, line_number + 1, line);
+
+            if let Some(setting) = line.strip_prefix("set ") {
+                let split = setting.find(' ').ok_or_else(error)?;
+                settings.push((setting[..split].to_string(), setting[split + 1..].to_string()));
+            } else if !line.trim().is_empty() {
+                let split = line.rfind(' ').ok_or_else(error)?;
+                let command = Command::parse(&line[..split]).ok_or_else(error)?;
+                let checksum = u64::from_str_radix(&line[split + 1..], 16).map_err(|_| error())?;
+                steps.push(Step { command, checksum });
+            }
+        }
+
+        if !settings.iter().any(|(key, _)| key == "seed") {
+            return Err(format!("{}: no seed recorded", path));
+        }
+
+        Ok(Replay { settings, steps })
+    }
+}
+
+//FNV-1a over everything a command can change, written out by hand so the
+//value doesn't depend on the standard library's hasher
+pub fn checksum(world: &World) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    let mut feed = |value: u64| {
+        for byte in value.to_le_bytes().iter() {
+            hash ^= *byte as u64;
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    };
+
+    let entities = world.entities();
+    let positions = world.read_storage::<Position>();
+    let combat_stats = world.read_storage::<CombatStats>();
+
+    for (entity, position) in (&entities, &positions).join() {
+        feed(entity.id() as u64);
+        feed(position.x as u64);
+        feed(position.y as u64);
+    }
+
+    for (entity, stats) in (&entities, &combat_stats).join() {
+        feed(entity.id() as u64);
+        feed(stats.hp as u64);
+    }
+
+    let map = world.fetch::<Map>();
+    for tile in map.tiles.iter() {
+        feed(tile.tile_type as u64);
+    }
+
+    hash
+}
+
+fn check_step(state: &mut App, turn: usize, step: &Step) -> Result<(), String> {
+    state.apply(step.command);
+
+    let actual = checksum(&state.world);
+    if actual != step.checksum {
+        return Err(format!(
+            "Replay diverged at command {} ({}): expected {:016x}, got {:016x}",
+            turn + 1,
+            step.command,
+            step.checksum,
+            actual
+        ));
+    }
+
+    Ok(())
+}
+
+pub fn verify(state: &mut App, replay: &Replay) -> Result<(), String> {
+    //the same opening turn the first frame would run
+    if state.run_state == State::Running {
+        state.run_turn();
+    }
+
+    for (turn, step) in replay.steps.iter().enumerate() {
+        check_step(state, turn, step)?;
+    }
+
+    Ok(())
+}
+
+//plays the commands back at a fixed pace, escape stops early
+pub fn play(
+    state: &mut App,
+    view: &mut View,
+    font: &mut FontCache,
+    cell_size: (u32, u32),
+    replay: &Replay
+) -> Result<(), String> {
+    let mut event_pump = view.event_pump()?;
+    let mut renderer = SdlRenderer { view, font, cell_size };
+    let mut steps = replay.steps.iter().enumerate();
+    let mut last_step = Instant::now();
+
+    loop {
+        state.tick(&mut renderer);
+
+        for event in event_pump.poll_iter() {
+            match event {
+                Event::Quit {..} |
+                Event::KeyDown { keycode: Some(Keycode::Escape), .. } => {
+                    return Ok(());
+                },
+
+                _ => {}
+            }
+        }
+
+        if last_step.elapsed() >= REPLAY_DELAY {
+            if let Some((turn, step)) = steps.next() {
+                check_step(state, turn, step)?;
+            }
+
+            last_step = Instant::now();
+        }
+    }
+}
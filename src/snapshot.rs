@@ -0,0 +1,56 @@
+use std::{
+    env,
+    fs,
+    path::PathBuf,
+};
+
+use crate::{
+    config::Config,
+    generate_map,
+    map::Map,
+    prefab::Legend,
+    SimpleRng,
+};
+
+//the small level every snapshot is taken from, with the rng as generation
+//left it so a game can carry on from there
+pub fn seeded_level() -> (Map, Legend, SimpleRng) {
+    let config = Config {
+        map_width: Some(60),
+        map_height: Some(30),
+        max_rooms: 6,
+        ..Config::default()
+    };
+
+    let mut rng = SimpleRng::new(1234);
+    let (map, legend) = generate_map(&config, &mut rng).expect("Could not generate the snapshot level");
+
+    (map, legend, rng)
+}
+
+//expected output lives in tests/snapshots, one file per snapshot, and is
+//only ever written when UPDATE_SNAPSHOTS is set, so that intended changes can
+//be accepted in one go. a missing file is a failure like any other mismatch
+pub fn assert_snapshot(name: &str, actual: &str) {
+    let path: PathBuf = [env!("CARGO_MANIFEST_DIR"), "tests", "snapshots", &format!("{}.txt", name)]
+        .iter()
+        .collect();
+
+    if env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        fs::create_dir_all(path.parent().unwrap()).expect("Could not create snapshot directory");
+        fs::write(&path, actual).expect("Could not write snapshot");
+        return;
+    }
+
+    let expected = match fs::read_to_string(&path) {
+        Ok(expected) => expected,
+        Err(e) => panic!(
+            "Could not read snapshot {}: {}\nrun the tests with UPDATE_SNAPSHOTS=1 to record it, got:\n{}",
+            path.display(),
+            e,
+            actual
+        ),
+    };
+
+    assert!(expected == actual, "{} no longer matches {}, got:\n{}", name, path.display(), actual);
+}
@@ -0,0 +1,189 @@
+use specs::prelude::*;
+
+use crate::{
+    components::*,
+    Color,
+    SimpleRng,
+};
+
+pub fn player(world: &mut World, position: Position) -> Entity {
+    world
+        .create_entity()
+        .with(Player)
+        .with(Name { name: "Player".to_string() })
+        .with(Faction { name: "player".to_string() })
+        .with(position)
+        .with(Renderable {
+            glyph: '@',
+            color: Color::RGB(0, 0, 255),
+        })
+        .with(Viewshed { visible_tiles: Vec::new(), range: 10, dirty: true })
+        .with(CombatStats {max_hp: 30, hp: 30, defense: 2, power: 5 })
+        .with(Regeneration { turns_per_hp: 10, progress: 0 })
+        .with(LightSource { radius: 6, color: Color::RGB(255, 220, 160), intensity: 1.0 })
+        .with(Perception { score: 6 })
+        .build()
+}
+
+pub fn dog(world: &mut World, position: Position) -> Entity {
+    world
+        .create_entity()
+        .with(Name { name: "Dog".to_string() })
+        .with(Faction { name: "player".to_string() })
+        .with(position)
+        .with(Renderable {
+            glyph: 'd',
+            color: Color::RGB(0, 255, 0),
+        })
+        .with(Viewshed { visible_tiles: Vec::new(), range: 8, dirty: true })
+        .with(Monster)
+        .with(Companion { leash: 4 })
+        .with(Memory { last_seen: None, search_turns: 0 })
+        .with(BlocksTile)
+        .with(CombatStats { max_hp: 20, hp: 20, defense: 1, power: 4 })
+        .with(Regeneration { turns_per_hp: 15, progress: 0 })
+        .build()
+}
+
+pub fn random_monster(world: &mut World, position: Position, idx: usize, rng: &mut SimpleRng) -> Entity {
+    if rng.roll(0, 2) == 1 {
+        goblin(world, position, idx)
+    } else {
+        kobold(world, position, idx)
+    }
+}
+
+pub fn goblin(world: &mut World, position: Position, idx: usize) -> Entity {
+    monster(
+        world,
+        position,
+        format!("Goblin #{}", idx),
+        "goblin",
+        Renderable { glyph: 'g', color: Color::RGB(255, 0, 0) },
+        CombatStats{ max_hp: 16, hp: 16, defense: 1, power: 4 },
+        Morale { threshold: 5, fleeing: false }
+    )
+}
+
+pub fn kobold(world: &mut World, position: Position, idx: usize) -> Entity {
+    monster(
+        world,
+        position,
+        format!("Kobold #{}", idx),
+        "kobold",
+        Renderable { glyph: 'k', color: Color::RGB(255, 128, 0) },
+        CombatStats{ max_hp: 12, hp: 12, defense: 0, power: 4 },
+        Morale { threshold: 6, fleeing: false }
+    )
+}
+
+fn monster(
+    world: &mut World,
+    position: Position,
+    name: String,
+    faction: &str,
+    renderable: Renderable,
+    stats: CombatStats,
+    morale: Morale
+) -> Entity {
+    world
+        .create_entity()
+        .with(Name { name })
+        .with(Faction { name: faction.to_string() })
+        .with(position)
+        .with(renderable)
+        .with(Viewshed { visible_tiles: Vec::new(), range: 8, dirty: true })
+        .with(Monster)
+        .with(Memory { last_seen: None, search_turns: 0 })
+        .with(BlocksTile)
+        .with(stats)
+        .with(Regeneration { turns_per_hp: 20, progress: 0 })
+        .with(morale)
+        .build()
+}
+
+pub fn torch(world: &mut World, position: Position) -> Entity {
+    world
+        .create_entity()
+        .with(Name { name: "torch".to_string() })
+        .with(position)
+        .with(Renderable {
+            glyph: '*',
+            color: Color::RGB(255, 160, 0),
+        })
+        .with(LightSource { radius: 8, color: Color::RGB(255, 180, 80), intensity: 1.2 })
+        .build()
+}
+
+pub fn glowing_fungus(world: &mut World, position: Position) -> Entity {
+    world
+        .create_entity()
+        .with(Name { name: "glowing fungus".to_string() })
+        .with(position)
+        .with(Renderable {
+            glyph: '%',
+            color: Color::RGB(80, 255, 200),
+        })
+        .with(LightSource { radius: 4, color: Color::RGB(80, 255, 200), intensity: 0.6 })
+        .build()
+}
+
+pub fn random_trap(world: &mut World, position: Position, rng: &mut SimpleRng) -> Entity {
+    let kind = match rng.roll(0, 4) {
+        0 => TrapKind::SpikePit,
+        1 => TrapKind::Teleporter,
+        2 => TrapKind::AlarmPlate,
+        _ => TrapKind::GasVent,
+    };
+
+    trap(world, position, kind)
+}
+
+pub fn trap(world: &mut World, position: Position, kind: TrapKind) -> Entity {
+    let name = match kind {
+        TrapKind::SpikePit => "spike pit",
+        TrapKind::Teleporter => "teleporter",
+        TrapKind::AlarmPlate => "alarm plate",
+        TrapKind::GasVent => "gas vent",
+    };
+
+    world
+        .create_entity()
+        .with(Name { name: name.to_string() })
+        .with(position)
+        .with(Renderable {
+            glyph: '^',
+            color: Color::RGB(255, 0, 255),
+        })
+        .with(Trap { kind, occupants: Vec::new() })
+        .with(Hidden)
+        .build()
+}
+
+pub fn key(world: &mut World, position: Position, lock: usize) -> Entity {
+    world
+        .create_entity()
+        .with(Name { name: "brass key".to_string() })
+        .with(position)
+        .with(Renderable {
+            glyph: '-',
+            color: Color::RGB(255, 255, 0),
+        })
+        .with(Key { lock })
+        .build()
+}
+
+//spawns by the names used in data files such as the prefab legend
+pub fn named(world: &mut World, name: &str, position: Position, idx: usize) -> Result<Entity, String> {
+    match name {
+        "goblin" => Ok(goblin(world, position, idx)),
+        "kobold" => Ok(kobold(world, position, idx)),
+        "torch" => Ok(torch(world, position)),
+        "glowing_fungus" => Ok(glowing_fungus(world, position)),
+        "alarm_plate" => Ok(trap(world, position, TrapKind::AlarmPlate)),
+        "gas_vent" => Ok(trap(world, position, TrapKind::GasVent)),
+        "spike_pit" => Ok(trap(world, position, TrapKind::SpikePit)),
+        "teleporter" => Ok(trap(world, position, TrapKind::Teleporter)),
+        other => Err(format!("Unknown spawn {}", other)),
+    }
+}
@@ -0,0 +1,231 @@
+use std::{
+    io::{
+        stdout,
+        Stdout,
+        Write,
+    },
+    time::Duration,
+};
+
+use crossterm::{
+    cursor::{
+        Hide,
+        MoveTo,
+        Show,
+    },
+    event::{
+        self,
+        Event,
+        KeyCode,
+        KeyEvent,
+    },
+    execute,
+    queue,
+    style::{
+        self,
+        Print,
+        SetBackgroundColor,
+        SetForegroundColor,
+    },
+    terminal::{
+        self,
+        Clear,
+        ClearType,
+        EnterAlternateScreen,
+        LeaveAlternateScreen,
+    },
+};
+
+use crate::{
+    app::{
+        App,
+        State,
+    },
+    Color,
+    components::Position,
+    keymap::{
+        Action,
+        Keymap,
+    },
+    map::Map,
+    renderer::{
+        Cell,
+        Renderer,
+        TextGrid,
+    },
+};
+
+const INPUT_POLL: Duration = Duration::from_millis(50);
+
+//draws into a text grid the size of the map, then writes the part of it
+//around the focus that fits in the terminal. what's already on screen is
+//remembered, so only cells that changed are written again
+pub struct TerminalRenderer {
+    grid: TextGrid,
+    focus: (usize, usize),
+    screen: Vec<Option<Cell>>,
+    screen_size: (usize, usize),
+    out: Stdout,
+}
+
+impl TerminalRenderer {
+    fn new(width: usize, height: usize) -> TerminalRenderer {
+        TerminalRenderer {
+            grid: TextGrid::new(width, height),
+            focus: (0, 0),
+            screen: Vec::new(),
+            screen_size: (0, 0),
+            out: stdout(),
+        }
+    }
+
+    fn flush(&mut self) -> crossterm::Result<()> {
+        let (columns, rows) = terminal::size()?;
+        let (columns, rows) = (columns as usize, rows as usize);
+
+        let origin = |focus: usize, visible: usize, total: usize| {
+            if total <= visible { 0 } else { focus.saturating_sub(visible / 2).min(total - visible) }
+        };
+        let origin_x = origin(self.focus.0, columns, self.grid.width);
+        let origin_y = origin(self.focus.1, rows, self.grid.height);
+
+        //a resized terminal may have kept anything, so it starts over blank
+        if self.screen_size != (columns, rows) {
+            queue!(self.out, Clear(ClearType::All))?;
+            self.screen = vec![None; columns * rows];
+            self.screen_size = (columns, rows);
+        }
+
+        let mut colors = None;
+        let mut cursor = None;
+        for row in 0..rows.min(self.grid.height) {
+            for column in 0..columns.min(self.grid.width) {
+                let cell = self.grid.cell(origin_x + column, origin_y + row);
+
+                let painted = &mut self.screen[(row * columns) + column];
+                if *painted == Some(cell) { continue }
+                *painted = Some(cell);
+
+                if cursor != Some((column, row)) {
+                    queue!(self.out, MoveTo(column as u16, row as u16))?;
+                }
+
+                //only switch colors when they change, most of a frame is walls and floor
+                if colors != Some((cell.foreground, cell.background)) {
+                    queue!(
+                        self.out,
+                        SetForegroundColor(ansi(cell.foreground)),
+                        SetBackgroundColor(ansi(cell.background))
+                    )?;
+                    colors = Some((cell.foreground, cell.background));
+                }
+
+                queue!(self.out, Print(cell.glyph))?;
+                cursor = Some((column + 1, row));
+            }
+        }
+
+        self.out.flush()?;
+        Ok(())
+    }
+}
+
+impl Renderer for TerminalRenderer {
+    fn clear(&mut self) {
+        self.grid.clear();
+    }
+
+    fn draw_glyph(&mut self, x: usize, y: usize, glyph: char, foreground: Color, background: Color) {
+        self.grid.draw_glyph(x, y, glyph, foreground, background);
+    }
+
+    fn draw_text(&mut self, x: usize, y: usize, text: &str, foreground: Color) {
+        self.grid.draw_text(x, y, text, foreground);
+    }
+
+    fn present(&mut self) {
+        self.flush().expect("Could not write to terminal");
+    }
+}
+
+fn ansi(color: Color) -> style::Color {
+    style::Color::Rgb { r: color.r, g: color.g, b: color.b }
+}
+
+pub fn run(state: &mut App, keymap: &Keymap) -> Result<(), String> {
+    let (width, height) = {
+        let map = state.world.fetch::<Map>();
+        (map.width, map.height)
+    };
+
+    let mut renderer = TerminalRenderer::new(width, height);
+
+    terminal::enable_raw_mode().map_err(|e| e.to_string())?;
+    let _guard = TerminalGuard;
+    execute!(renderer.out, EnterAlternateScreen, Hide).map_err(|e| e.to_string())?;
+
+    game_loop(state, keymap, &mut renderer)
+}
+
+//puts the terminal back the way it was when dropped, so it's restored whether
+//the game loop returns, fails or panics
+struct TerminalGuard;
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = execute!(stdout(), Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+//a frame is only drawn when something can have changed, a terminal over ssh
+//shouldn't be sent the same screen twenty times a second
+fn game_loop(state: &mut App, keymap: &Keymap, renderer: &mut TerminalRenderer) -> Result<(), String> {
+    let mut redraw = true;
+
+    loop {
+        if redraw || state.run_state == State::Running {
+            let player_position = *state.world.fetch::<Position>();
+            renderer.focus = (player_position.x, player_position.y);
+            state.tick(renderer);
+            redraw = false;
+        }
+
+        if !event::poll(INPUT_POLL).map_err(|e| e.to_string())? { continue }
+
+        let code = match event::read().map_err(|e| e.to_string())? {
+            Event::Key(KeyEvent { code, .. }) => code,
+            Event::Resize(_, _) => {
+                redraw = true;
+                continue;
+            },
+            _ => continue,
+        };
+
+        match key_name(code).and_then(|key| keymap.action(&key)) {
+            Some(Action::Perform(command)) => state.apply(command),
+            Some(Action::ToggleConsole) => state.console = !state.console,
+            Some(Action::Quit) => return Ok(()),
+            None => continue,
+        }
+
+        redraw = true;
+    }
+}
+
+//terminals can't tell the number pad from the digit row, so digits are read
+//as the number pad, which is what they're bound to
+fn key_name(code: KeyCode) -> Option<String> {
+    let name = match code {
+        KeyCode::Left => "left".to_string(),
+        KeyCode::Right => "right".to_string(),
+        KeyCode::Up => "up".to_string(),
+        KeyCode::Down => "down".to_string(),
+        KeyCode::Esc => "escape".to_string(),
+        KeyCode::Char(digit @ '1'..='9') => format!("kp{}", digit),
+        KeyCode::Char(c) => c.to_lowercase().to_string(),
+        _ => return None,
+    };
+
+    Some(name)
+}
@@ -0,0 +1,33 @@
+use specs::prelude::*;
+use super::{CombatStats, IncomingDamage, Map, MessageLog, Name, Player, Position};
+
+pub struct TerrainEffects {}
+
+impl<'a> System<'a> for TerrainEffects {
+    type SystemData = ( Entities<'a>,
+                        ReadExpect<'a, Map>,
+                        WriteExpect<'a, MessageLog>,
+                        ReadStorage<'a, Position>,
+                        ReadStorage<'a, CombatStats>,
+                        ReadStorage<'a, Player>,
+                        ReadStorage<'a, Name>,
+                        WriteStorage<'a, IncomingDamage> );
+
+    fn run(&mut self, data : Self::SystemData) {
+        let (entities, map, mut messages, positions, combat_stats, players, names, mut inflict_damage) = data;
+
+        for (entity, position, _) in (&entities, &positions, &combat_stats).join() {
+            let properties = map[(position.x, position.y)].tile_type.properties();
+
+            if properties.damage_per_turn > 0 {
+                IncomingDamage::add_damage(&mut inflict_damage, entity, properties.damage_per_turn);
+
+                if players.contains(entity) {
+                    messages.push(format!("The {} burns you!", properties.name));
+                } else if let Some(name) = names.get(entity) {
+                    messages.push(format!("{} is burned by the {}!", name.name, properties.name));
+                }
+            }
+        }
+    }
+}
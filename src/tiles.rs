@@ -0,0 +1,178 @@
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum TileType {
+    Chasm,
+    ClosedDoor,
+    DeepWater,
+    Floor,
+    Lava,
+    OpenDoor,
+    Rubble,
+    SecretDoor,
+    ShallowWater,
+    TallGrass,
+    Wall,
+}
+
+pub struct TileProperties {
+    pub name: &'static str,
+    pub glyph: char,
+    pub foreground: (u8, u8, u8),
+    pub background: (u8, u8, u8),
+    pub blocks_movement: bool,
+    pub blocks_sight: bool,
+    pub movement_cost: f64,
+    pub sound_dampening: usize,
+    //applied every turn an entity ends on the tile, not just the turn it arrives
+    pub damage_per_turn: usize,
+}
+
+const CHASM: TileProperties = TileProperties {
+    name: "chasm",
+    glyph: ' ',
+    foreground: (0, 0, 0),
+    background: (0, 0, 0),
+    blocks_movement: true,
+    blocks_sight: false,
+    movement_cost: f64::INFINITY,
+    sound_dampening: 1,
+    damage_per_turn: 0,
+};
+
+const CLOSED_DOOR: TileProperties = TileProperties {
+    name: "closed door",
+    glyph: '+',
+    foreground: (170, 110, 50),
+    background: (0, 0, 0),
+    blocks_movement: true,
+    blocks_sight: true,
+    movement_cost: f64::INFINITY,
+    sound_dampening: 2,
+    damage_per_turn: 0,
+};
+
+const DEEP_WATER: TileProperties = TileProperties {
+    name: "deep water",
+    glyph: '~',
+    foreground: (60, 90, 255),
+    background: (0, 0, 120),
+    blocks_movement: true,
+    blocks_sight: false,
+    movement_cost: f64::INFINITY,
+    sound_dampening: 1,
+    damage_per_turn: 0,
+};
+
+const FLOOR: TileProperties = TileProperties {
+    name: "floor",
+    glyph: '.',
+    foreground: (255, 255, 255),
+    background: (0, 0, 0),
+    blocks_movement: false,
+    blocks_sight: false,
+    movement_cost: 1.0,
+    sound_dampening: 1,
+    damage_per_turn: 0,
+};
+
+const LAVA: TileProperties = TileProperties {
+    name: "lava",
+    glyph: '~',
+    foreground: (255, 200, 0),
+    background: (200, 40, 0),
+    blocks_movement: false,
+    blocks_sight: false,
+    movement_cost: 25.0,
+    sound_dampening: 1,
+    damage_per_turn: 10,
+};
+
+const OPEN_DOOR: TileProperties = TileProperties {
+    name: "open door",
+    glyph: '\'',
+    foreground: (170, 110, 50),
+    background: (0, 0, 0),
+    blocks_movement: false,
+    blocks_sight: false,
+    movement_cost: 1.0,
+    sound_dampening: 1,
+    damage_per_turn: 0,
+};
+
+const RUBBLE: TileProperties = TileProperties {
+    name: "rubble",
+    glyph: ',',
+    foreground: (150, 140, 120),
+    background: (0, 0, 0),
+    blocks_movement: false,
+    blocks_sight: false,
+    movement_cost: 2.0,
+    sound_dampening: 1,
+    damage_per_turn: 0,
+};
+
+//indistinguishable from a wall until it's found
+const SECRET_DOOR: TileProperties = TileProperties {
+    name: "wall",
+    glyph: '\u{2593}',
+    foreground: (255, 255, 255),
+    background: (0, 0, 0),
+    blocks_movement: true,
+    blocks_sight: true,
+    movement_cost: f64::INFINITY,
+    sound_dampening: 4,
+    damage_per_turn: 0,
+};
+
+const SHALLOW_WATER: TileProperties = TileProperties {
+    name: "shallow water",
+    glyph: '~',
+    foreground: (120, 160, 255),
+    background: (0, 0, 0),
+    blocks_movement: false,
+    blocks_sight: false,
+    movement_cost: 2.0,
+    sound_dampening: 1,
+    damage_per_turn: 0,
+};
+
+const TALL_GRASS: TileProperties = TileProperties {
+    name: "tall grass",
+    glyph: '"',
+    foreground: (0, 200, 0),
+    background: (0, 0, 0),
+    blocks_movement: false,
+    blocks_sight: true,
+    movement_cost: 1.0,
+    sound_dampening: 2,
+    damage_per_turn: 0,
+};
+
+const WALL: TileProperties = TileProperties {
+    name: "wall",
+    glyph: '\u{2593}',
+    foreground: (255, 255, 255),
+    background: (0, 0, 0),
+    blocks_movement: true,
+    blocks_sight: true,
+    movement_cost: f64::INFINITY,
+    sound_dampening: 4,
+    damage_per_turn: 0,
+};
+
+impl TileType {
+    pub fn properties(&self) -> &'static TileProperties {
+        match self {
+            TileType::Chasm => &CHASM,
+            TileType::ClosedDoor => &CLOSED_DOOR,
+            TileType::DeepWater => &DEEP_WATER,
+            TileType::Floor => &FLOOR,
+            TileType::Lava => &LAVA,
+            TileType::OpenDoor => &OPEN_DOOR,
+            TileType::Rubble => &RUBBLE,
+            TileType::SecretDoor => &SECRET_DOOR,
+            TileType::ShallowWater => &SHALLOW_WATER,
+            TileType::TallGrass => &TALL_GRASS,
+            TileType::Wall => &WALL,
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use specs::prelude::*;
+use crate::{
+    components::*,
+    hearing::Noise,
+    Map,
+    MessageLog,
+    SimpleRng,
+};
+
+pub const ALARM_NOISE: usize = 30;
+pub const GAS_DAMAGE: usize = 3;
+pub const SPIKE_DAMAGE: usize = 6;
+
+pub struct TrapTrigger {}
+
+impl<'a> System<'a> for TrapTrigger {
+    type SystemData = ( Entities<'a>,
+                        ReadExpect<'a, Map>,
+                        WriteExpect<'a, SimpleRng>,
+                        WriteExpect<'a, MessageLog>,
+                        WriteExpect<'a, Vec<Noise>>,
+                        WriteExpect<'a, Position>,
+                        WriteStorage<'a, Position>,
+                        WriteStorage<'a, Viewshed>,
+                        WriteStorage<'a, Hidden>,
+                        WriteStorage<'a, IncomingDamage>,
+                        WriteStorage<'a, Trap>,
+                        ReadStorage<'a, CombatStats>,
+                        ReadStorage<'a, Player>,
+                        ReadStorage<'a, Name> );
+
+    fn run(&mut self, data : Self::SystemData) {
+        let (
+            entities,
+            map,
+            mut rng,
+            mut messages,
+            mut noises,
+            mut player_position,
+            mut positions,
+            mut viewsheds,
+            mut hidden,
+            mut inflict_damage,
+            mut traps,
+            combat_stats,
+            players,
+            names
+        ) = data;
+
+        let mut triggered: Vec<(Entity, TrapKind, Position, Entity)> = Vec::new();
+        for (trap_entity, trap, position) in (&entities, &mut traps, &positions).join() {
+            let occupants: Vec<Entity> = map[(position.x, position.y)].entities
+                .iter()
+                .copied()
+                .filter(|&victim| victim != trap_entity && combat_stats.contains(victim))
+                .collect();
+
+            for &victim in occupants.iter() {
+                if !trap.occupants.contains(&victim) {
+                    triggered.push((trap_entity, trap.kind, *position, victim));
+                }
+            }
+
+            trap.occupants = occupants;
+        }
+
+        for (trap_entity, kind, position, victim) in triggered {
+            //traps sprung out of sight stay hidden from the player
+            if players.contains(victim) || map[(position.x, position.y)].visible {
+                hidden.remove(trap_entity);
+
+                let victim_name = if players.contains(victim) {
+                    "You".to_string()
+                } else {
+                    names.get(victim).map_or("Something".to_string(), |name| name.name.clone())
+                };
+                let trap_name = names.get(trap_entity).map_or("trap".to_string(), |name| name.name.clone());
+                messages.push(format!("{} triggered a {}!", victim_name, trap_name));
+            }
+
+            match kind {
+                TrapKind::SpikePit => {
+                    IncomingDamage::add_damage(&mut inflict_damage, victim, SPIKE_DAMAGE);
+                },
+
+                TrapKind::Teleporter => {
+                    if let Some((x, y)) = random_open_tile(&map, &mut rng) {
+                        positions.insert(victim, Position { x, y }).expect("Could not teleport");
+                        if players.contains(victim) {
+                            *player_position = Position { x, y };
+                        }
+                        if let Some(viewshed) = viewsheds.get_mut(victim) {
+                            viewshed.dirty = true;
+                        }
+                    }
+                },
+
+                TrapKind::AlarmPlate => {
+                    noises.push(Noise { x: position.x, y: position.y, volume: ALARM_NOISE });
+                },
+
+                TrapKind::GasVent => {
+                    for (entity, other_position, _) in (&entities, &positions, &combat_stats).join() {
+                        if other_position.distance(&position) <= 1 {
+                            IncomingDamage::add_damage(&mut inflict_damage, entity, GAS_DAMAGE);
+                        }
+                    }
+                },
+            }
+        }
+    }
+}
+
+fn random_open_tile(map: &Map, rng: &mut SimpleRng) -> Option<(usize, usize)> {
+    for _ in 0..100 {
+        let tile = (rng.roll(0, map.width), rng.roll(0, map.height));
+        if !map[tile].blocked && map[tile].tile_type.properties().damage_per_turn == 0 {
+            return Some(tile);
+        }
+    }
+
+    None
+}
@@ -0,0 +1,216 @@
+use std::collections::VecDeque;
+
+use crate::{
+    tiles::TileType,
+};
+
+use wavebreaker_util::algorithms::simple_rng::SimpleRng;
+
+//how many decisions can be undone before a contradiction restarts the whole wave
+const MAX_BACKTRACK_DEPTH: usize = 32;
+
+const DIRECTIONS: [(i64, i64); 4] = [(0, -1), (1, 0), (0, 1), (-1, 0)];
+
+pub struct Constraints {
+    tiles: Vec<TileType>,
+    weights: Vec<usize>,
+    //allowed[direction][tile] is a mask of the tiles that may sit next to tile in that direction
+    allowed: [Vec<u64>; 4],
+}
+
+impl Constraints {
+    pub fn learn<F: Fn(usize, usize) -> TileType>(width: usize, height: usize, tile_at: F) -> Constraints {
+        let mut constraints = Constraints {
+            tiles: Vec::new(),
+            weights: Vec::new(),
+            allowed: [Vec::new(), Vec::new(), Vec::new(), Vec::new()],
+        };
+
+        for x in 0..width {
+            for y in 0..height {
+                let tile = constraints.index_of(tile_at(x, y));
+                constraints.weights[tile] += 1;
+
+                for (direction, &(d_x, d_y)) in DIRECTIONS.iter().enumerate() {
+                    let next_x = x as i64 + d_x;
+                    let next_y = y as i64 + d_y;
+                    if next_x < 0 || next_y < 0 || next_x >= width as i64 || next_y >= height as i64 {
+                        continue;
+                    }
+
+                    let neighbor = constraints.index_of(tile_at(next_x as usize, next_y as usize));
+                    constraints.allowed[direction][tile] |= 1 << neighbor;
+                }
+            }
+        }
+
+        debug!("Learned adjacency for {} tile types", constraints.tiles.len());
+        constraints
+    }
+
+    fn index_of(&mut self, tile_type: TileType) -> usize {
+        match self.tiles.iter().position(|&known| known == tile_type) {
+            Some(idx) => idx,
+            None => {
+                self.tiles.push(tile_type);
+                self.weights.push(0);
+                for allowed in self.allowed.iter_mut() {
+                    allowed.push(0);
+                }
+
+                self.tiles.len() - 1
+            },
+        }
+    }
+}
+
+struct Wave<'a> {
+    constraints: &'a Constraints,
+    width: usize,
+    height: usize,
+    cells: Vec<u64>,
+}
+
+impl<'a> Wave<'a> {
+    fn neighbor(&self, cell: usize, (d_x, d_y): (i64, i64)) -> Option<usize> {
+        let x = (cell / self.height) as i64 + d_x;
+        let y = (cell % self.height) as i64 + d_y;
+        if x < 0 || y < 0 || x >= self.width as i64 || y >= self.height as i64 {
+            return None;
+        }
+
+        Some((x as usize * self.height) + y as usize)
+    }
+
+    //false on a contradiction, when some cell is left with no options at all
+    fn propagate(&mut self, mut pending: Vec<usize>) -> bool {
+        while let Some(cell) = pending.pop() {
+            for (direction, &offset) in DIRECTIONS.iter().enumerate() {
+                let neighbor = match self.neighbor(cell, offset) {
+                    Some(neighbor) => neighbor,
+                    None => continue,
+                };
+
+                let mut supported = 0;
+                for tile in 0..self.constraints.tiles.len() {
+                    if self.cells[cell] & (1 << tile) != 0 {
+                        supported |= self.constraints.allowed[direction][tile];
+                    }
+                }
+
+                let remaining = self.cells[neighbor] & supported;
+                if remaining != self.cells[neighbor] {
+                    if remaining == 0 { return false }
+
+                    self.cells[neighbor] = remaining;
+                    pending.push(neighbor);
+                }
+            }
+        }
+
+        true
+    }
+
+    //the undecided cell with the fewest options, ties broken at random
+    fn lowest_entropy(&self, rng: &mut SimpleRng) -> Option<usize> {
+        let lowest = self.cells
+            .iter()
+            .map(|options| options.count_ones())
+            .filter(|&count| count > 1)
+            .min()?;
+
+        let tied: Vec<usize> = (0..self.cells.len())
+            .filter(|&cell| self.cells[cell].count_ones() == lowest)
+            .collect();
+
+        Some(tied[rng.roll(0, tied.len())])
+    }
+
+    fn choose(&self, cell: usize, rng: &mut SimpleRng) -> usize {
+        let options: Vec<usize> = (0..self.constraints.tiles.len())
+            .filter(|tile| self.cells[cell] & (1 << tile) != 0)
+            .collect();
+
+        let total: usize = options.iter().map(|&tile| self.constraints.weights[tile]).sum();
+        let mut roll = rng.roll(0, total);
+        for &tile in options.iter() {
+            if roll < self.constraints.weights[tile] { return tile }
+            roll -= self.constraints.weights[tile];
+        }
+
+        options[options.len() - 1]
+    }
+}
+
+//None if the wave contradicted itself further back than it could undo
+pub fn collapse(
+    constraints: &Constraints,
+    width: usize,
+    height: usize,
+    rng: &mut SimpleRng
+) -> Option<Vec<TileType>> {
+    let mut wave = Wave {
+        constraints,
+        width,
+        height,
+        cells: vec![(1 << constraints.tiles.len()) - 1; width * height],
+    };
+
+    //maps are walled in, so the border is decided before anything else
+    let mut border = Vec::new();
+    if let Some(wall) = constraints.tiles.iter().position(|&tile| tile == TileType::Wall) {
+        for cell in 0..wave.cells.len() {
+            let (x, y) = (cell / height, cell % height);
+            if x == 0 || y == 0 || x == width - 1 || y == height - 1 {
+                wave.cells[cell] = 1 << wall;
+                border.push(cell);
+            }
+        }
+    }
+
+    if !wave.propagate(border) { return None }
+
+    let mut decisions: VecDeque<(Vec<u64>, usize, usize)> = VecDeque::new();
+    let mut backtracks = 0;
+
+    while let Some(cell) = wave.lowest_entropy(rng) {
+        let tile = wave.choose(cell, rng);
+        let snapshot = wave.cells.clone();
+
+        wave.cells[cell] = 1 << tile;
+        if wave.propagate(vec![cell]) {
+            decisions.push_back((snapshot, cell, tile));
+            if decisions.len() > MAX_BACKTRACK_DEPTH { decisions.pop_front(); }
+            continue;
+        }
+
+        //undo the decision that failed and rule its tile out, walking further
+        //back whenever that leaves the cell with nothing else to try
+        let (mut snapshot, mut cell, mut tile) = (snapshot, cell, tile);
+        loop {
+            backtracks += 1;
+            wave.cells = snapshot;
+            wave.cells[cell] &= !(1 << tile);
+
+            if wave.cells[cell] != 0 && wave.propagate(vec![cell]) { break }
+
+            match decisions.pop_back() {
+                Some(decision) => {
+                    snapshot = decision.0;
+                    cell = decision.1;
+                    tile = decision.2;
+                },
+                None => {
+                    warn!("Wave collapse gave up after {} backtracks", backtracks);
+                    return None;
+                },
+            }
+        }
+    }
+
+    info!("Wave collapsed with {} backtracks", backtracks);
+    Some(wave.cells
+        .iter()
+        .map(|options| constraints.tiles[options.trailing_zeros() as usize])
+        .collect())
+}